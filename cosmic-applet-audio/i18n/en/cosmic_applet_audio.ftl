@@ -3,5 +3,30 @@ input = Input
 show-media-controls = Show Media Controls on Top Panel
 sound-settings = Sound Settings...
 disconnected = PulseAudio Disconnected
+reconnecting = Trying to reconnect...
+retry = Retry
 no-device = No device selected
 unknown-artist = Unknown
+applications = Applications
+recording = Recording
+profiles = Profiles
+no-profile = No profile selected
+unplugged = unplugged
+media-player = Media Player
+mute = Mute
+unmute = Unmute
+balance = Balance
+balance-center = Centered
+balance-left = {$percent}% left
+balance-right = {$percent}% right
+left = Left
+right = Right
+max-volume = Maximum Volume
+edit-devices = Edit Devices
+edit-devices-description = Rename or hide devices
+combine-outputs = Play on Multiple Outputs
+combine-active = Playing on the selected outputs
+combine-inactive = Off
+combine = Play on selected outputs
+uncombine = Stop playing on multiple outputs
+limited-backend = Application volumes, profiles, ports and combined outputs need pipewire-pulse.
@@ -0,0 +1,270 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{cell::Cell, rc::Rc};
+
+use libpulse_binding::{
+    channelmap::Map as ChannelMap,
+    volume::{ChannelVolumes, Volume},
+};
+
+use crate::pulse::{DeviceInfo, StreamInfo};
+
+use super::{Backend, Change, Error, Features, Result};
+
+/// An in-memory sound server.
+///
+/// It starts out with a few devices and streams, and applies requests to its
+/// own state, reporting changes the same way a real server would.
+#[derive(Debug)]
+pub struct MockBackend {
+    sinks: Vec<DeviceInfo>,
+    sources: Vec<DeviceInfo>,
+    default_sink: u32,
+    default_source: u32,
+    sink_inputs: Vec<StreamInfo>,
+    source_outputs: Vec<StreamInfo>,
+    changes: Vec<Change>,
+    /// Whether the server is up, shared with whoever stops it.
+    running: Rc<Cell<bool>>,
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self {
+            sinks: vec![
+                device(0, "mock_output.speakers", "Speakers"),
+                device(1, "mock_output.headphones", "Headphones"),
+            ],
+            sources: vec![device(0, "mock_input.microphone", "Microphone")],
+            default_sink: 0,
+            default_source: 0,
+            sink_inputs: vec![stream(0, 0, "Music Player", "multimedia-audio-player")],
+            source_outputs: vec![stream(0, 0, "Voice Recorder", "audio-input-microphone")],
+            changes: Vec::new(),
+            running: Rc::new(Cell::new(true)),
+        }
+    }
+}
+
+impl MockBackend {
+    /// A server that is lost once `running` is cleared.
+    #[cfg(test)]
+    pub fn with_server(running: Rc<Cell<bool>>) -> Self {
+        Self {
+            running,
+            ..Self::default()
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.running.get() {
+            Ok(())
+        } else {
+            Err(Error::Disconnected)
+        }
+    }
+
+    fn changed(&mut self, change: Change) {
+        if !self.changes.contains(&change) {
+            self.changes.push(change);
+        }
+    }
+}
+
+fn device(index: u32, name: &str, description: &str) -> DeviceInfo {
+    let mut volume = ChannelVolumes::default();
+    volume.set(2, Volume::NORMAL);
+    let mut channel_map = ChannelMap::default();
+    channel_map.init_stereo();
+    DeviceInfo {
+        name: Some(name.to_string()),
+        description: Some(description.to_string()),
+        volume,
+        channel_map,
+        mute: false,
+        index,
+        ports: Vec::new(),
+        active_port: None,
+    }
+}
+
+fn stream(index: u32, device: u32, application_name: &str, icon_name: &str) -> StreamInfo {
+    let mut volume = ChannelVolumes::default();
+    volume.set(2, Volume::NORMAL);
+    StreamInfo {
+        index,
+        device,
+        application_name: Some(application_name.to_string()),
+        media_name: None,
+        icon_name: Some(icon_name.to_string()),
+        volume,
+        mute: false,
+    }
+}
+
+fn find<'a>(devices: &'a mut [DeviceInfo], name: &str) -> Option<&'a mut DeviceInfo> {
+    devices.iter_mut().find(|d| d.name.as_deref() == Some(name))
+}
+
+impl Backend for MockBackend {
+    fn features(&self) -> Features {
+        Features {
+            streams: true,
+            ..Features::default()
+        }
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.running.get()
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<Change>> {
+        self.check()?;
+        Ok(std::mem::take(&mut self.changes))
+    }
+
+    fn get_sinks(&mut self) -> Result<Vec<DeviceInfo>> {
+        self.check()?;
+        Ok(self.sinks.clone())
+    }
+
+    fn get_sources(&mut self) -> Result<Vec<DeviceInfo>> {
+        self.check()?;
+        Ok(self.sources.clone())
+    }
+
+    fn get_default_sink(&mut self) -> Result<DeviceInfo> {
+        self.check()?;
+        self.sinks
+            .iter()
+            .find(|d| d.index == self.default_sink)
+            .cloned()
+            .ok_or(Error::Misc("no default sink".to_string()))
+    }
+
+    fn get_default_source(&mut self) -> Result<DeviceInfo> {
+        self.check()?;
+        self.sources
+            .iter()
+            .find(|d| d.index == self.default_source)
+            .cloned()
+            .ok_or(Error::Misc("no default source".to_string()))
+    }
+
+    fn set_default_sink(&mut self, name: &str) -> bool {
+        let Some(index) = find(&mut self.sinks, name).map(|d| d.index) else {
+            return false;
+        };
+        for stream in &mut self.sink_inputs {
+            if stream.device == self.default_sink {
+                stream.device = index;
+            }
+        }
+        self.default_sink = index;
+        self.changed(Change::Server);
+        self.changed(Change::SinkInputs);
+        true
+    }
+
+    fn set_default_source(&mut self, name: &str) -> bool {
+        let Some(index) = find(&mut self.sources, name).map(|d| d.index) else {
+            return false;
+        };
+        for stream in &mut self.source_outputs {
+            if stream.device == self.default_source {
+                stream.device = index;
+            }
+        }
+        self.default_source = index;
+        self.changed(Change::Server);
+        self.changed(Change::SourceOutputs);
+        true
+    }
+
+    fn set_sink_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
+        if let Some(device) = find(&mut self.sinks, name) {
+            device.volume = *volume;
+            self.changed(Change::Sinks);
+        }
+    }
+
+    fn set_source_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
+        if let Some(device) = find(&mut self.sources, name) {
+            device.volume = *volume;
+            self.changed(Change::Sources);
+        }
+    }
+
+    fn set_sink_mute_by_name(&mut self, name: &str, mute: bool) {
+        if let Some(device) = find(&mut self.sinks, name) {
+            device.mute = mute;
+            self.changed(Change::Sinks);
+        }
+    }
+
+    fn set_source_mute_by_name(&mut self, name: &str, mute: bool) {
+        if let Some(device) = find(&mut self.sources, name) {
+            device.mute = mute;
+            self.changed(Change::Sources);
+        }
+    }
+
+    fn get_sink_input_list(&mut self) -> Result<Vec<StreamInfo>> {
+        self.check()?;
+        Ok(self.sink_inputs.clone())
+    }
+
+    fn set_sink_input_volume(&mut self, index: u32, volume: &ChannelVolumes) {
+        if let Some(stream) = self.sink_inputs.iter_mut().find(|s| s.index == index) {
+            stream.volume = *volume;
+            self.changed(Change::SinkInputs);
+        }
+    }
+
+    fn set_sink_input_mute(&mut self, index: u32, mute: bool) {
+        if let Some(stream) = self.sink_inputs.iter_mut().find(|s| s.index == index) {
+            stream.mute = mute;
+            self.changed(Change::SinkInputs);
+        }
+    }
+
+    fn move_sink_input(&mut self, index: u32, sink: &str) {
+        let Some(device) = find(&mut self.sinks, sink).map(|d| d.index) else {
+            return;
+        };
+        if let Some(stream) = self.sink_inputs.iter_mut().find(|s| s.index == index) {
+            stream.device = device;
+            self.changed(Change::SinkInputs);
+        }
+    }
+
+    fn get_source_output_list(&mut self) -> Result<Vec<StreamInfo>> {
+        self.check()?;
+        Ok(self.source_outputs.clone())
+    }
+
+    fn set_source_output_volume(&mut self, index: u32, volume: &ChannelVolumes) {
+        if let Some(stream) = self.source_outputs.iter_mut().find(|s| s.index == index) {
+            stream.volume = *volume;
+            self.changed(Change::SourceOutputs);
+        }
+    }
+
+    fn set_source_output_mute(&mut self, index: u32, mute: bool) {
+        if let Some(stream) = self.source_outputs.iter_mut().find(|s| s.index == index) {
+            stream.mute = mute;
+            self.changed(Change::SourceOutputs);
+        }
+    }
+
+    fn move_source_output(&mut self, index: u32, source: &str) {
+        let Some(device) = find(&mut self.sources, source).map(|d| d.index) else {
+            return;
+        };
+        if let Some(stream) = self.source_outputs.iter_mut().find(|s| s.index == index) {
+            stream.device = device;
+            self.changed(Change::SourceOutputs);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use libpulse_binding::volume::ChannelVolumes;
+
+use crate::pulse::{CardInfo, DeviceInfo, PulseServer, StreamInfo};
+
+use self::{mock::MockBackend, pipewire::PipeWireServer};
+
+pub(crate) mod mock;
+mod pipewire;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Debug)]
+pub enum Error {
+    Connect(String),
+    Disconnected,
+    Misc(String),
+}
+
+/// A kind of server object that changed, telling the applet what to request again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Sinks,
+    Sources,
+    SinkInputs,
+    SourceOutputs,
+    Cards,
+    /// The default sink or source.
+    Server,
+}
+
+/// Optional features of a backend, so that the applet can leave out the
+/// controls a backend doesn't support.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Features {
+    /// Volume and device of application streams.
+    pub streams: bool,
+    /// Profiles of sound cards.
+    pub cards: bool,
+    /// Ports of sinks and sources.
+    pub ports: bool,
+    /// Sinks playing to several outputs at once.
+    pub combined_sink: bool,
+}
+
+impl Features {
+    pub const ALL: Self = Self {
+        streams: true,
+        cards: true,
+        ports: true,
+        combined_sink: true,
+    };
+}
+
+/// Operations the applet needs from a sound server.
+///
+/// Backends are driven from a single thread and may block while waiting for
+/// the server. Streams, cards and ports are optional, and do nothing unless a
+/// backend implements them and reports them in [`Backend::features`].
+pub trait Backend {
+    /// Optional features this backend implements.
+    fn features(&self) -> Features {
+        Features::default()
+    }
+
+    /// Whether the server can still be reached.
+    fn is_connected(&mut self) -> bool;
+
+    /// Dispatch pending server events without blocking and return what
+    /// changed since the last call, or [`Error::Disconnected`] once the
+    /// server went away.
+    fn poll_events(&mut self) -> Result<Vec<Change>>;
+
+    fn get_sinks(&mut self) -> Result<Vec<DeviceInfo>>;
+
+    fn get_sources(&mut self) -> Result<Vec<DeviceInfo>>;
+
+    fn get_default_sink(&mut self) -> Result<DeviceInfo>;
+
+    fn get_default_source(&mut self) -> Result<DeviceInfo>;
+
+    /// Make the named sink the default, moving streams that were playing to
+    /// the previous default.
+    fn set_default_sink(&mut self, name: &str) -> bool;
+
+    /// Make the named source the default, moving streams that were recording
+    /// from the previous default.
+    fn set_default_source(&mut self, name: &str) -> bool;
+
+    fn set_sink_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes);
+
+    fn set_source_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes);
+
+    fn set_sink_mute_by_name(&mut self, name: &str, mute: bool);
+
+    fn set_source_mute_by_name(&mut self, name: &str, mute: bool);
+
+    fn get_sink_input_list(&mut self) -> Result<Vec<StreamInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn set_sink_input_volume(&mut self, _index: u32, _volume: &ChannelVolumes) {}
+
+    fn set_sink_input_mute(&mut self, _index: u32, _mute: bool) {}
+
+    fn move_sink_input(&mut self, _index: u32, _sink: &str) {}
+
+    fn get_source_output_list(&mut self) -> Result<Vec<StreamInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn set_source_output_volume(&mut self, _index: u32, _volume: &ChannelVolumes) {}
+
+    fn set_source_output_mute(&mut self, _index: u32, _mute: bool) {}
+
+    fn move_source_output(&mut self, _index: u32, _source: &str) {}
+
+    fn get_cards(&mut self) -> Result<Vec<CardInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn set_card_profile(&mut self, _index: u32, _profile: &str) {}
+
+    fn set_sink_port_by_name(&mut self, _name: &str, _port: &str) {}
+
+    fn set_source_port_by_name(&mut self, _name: &str, _port: &str) {}
+
+    /// Load a sink playing to all the given sinks at once, and return the
+    /// index of its module.
+    fn load_combined_sink(&mut self, _sinks: &[String]) -> Result<u32> {
+        Err(Error::Misc("combined sinks are not supported".to_string()))
+    }
+
+    fn unload_module(&mut self, _index: u32) {}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BackendType {
+    /// libpulse, which also covers PipeWire through pipewire-pulse.
+    #[default]
+    Pulse,
+    PipeWire,
+    /// An in-memory server, for running the applet without a sound server.
+    Mock,
+}
+
+impl BackendType {
+    // The mock backend is never picked automatically
+    fn next(self) -> Option<Self> {
+        match self {
+            Self::Pulse => Some(Self::PipeWire),
+            Self::PipeWire | Self::Mock => None,
+        }
+    }
+
+    // Backend forced through the `COSMIC_AUDIO_BACKEND` environment variable
+    fn from_env() -> Option<Self> {
+        match std::env::var("COSMIC_AUDIO_BACKEND").ok()?.as_str() {
+            "pulse" => Some(Self::Pulse),
+            "pipewire" => Some(Self::PipeWire),
+            "mock" => Some(Self::Mock),
+            other => {
+                tracing::warn!("Unknown audio backend: {}", other);
+                None
+            }
+        }
+    }
+
+    pub fn connect(self) -> Result<Box<dyn Backend>> {
+        match self {
+            Self::Pulse => {
+                let mut server = PulseServer::connect()
+                    .and_then(|server| server.init())
+                    .map_err(|err| Error::Connect(format!("{:?}", err)))?;
+                if let Err(err) = server.subscribe() {
+                    tracing::warn!("Failed to subscribe to server events: {:?}", err);
+                }
+                Ok(Box::new(server))
+            }
+            Self::PipeWire => Ok(Box::new(PipeWireServer::connect()?)),
+            Self::Mock => Ok(Box::new(MockBackend::default())),
+        }
+    }
+}
+
+/// Connect to the first sound server that is available, unless one was
+/// chosen through the environment, and return which kind it is.
+pub fn connect() -> Result<(BackendType, Box<dyn Backend>)> {
+    if let Some(backend_type) = BackendType::from_env() {
+        return Ok((backend_type, backend_type.connect()?));
+    }
+
+    let mut backend_type = BackendType::default();
+    loop {
+        match backend_type.connect() {
+            Ok(backend) => {
+                tracing::info!("Using {:?} audio backend", backend_type);
+                if backend.features() != Features::ALL {
+                    tracing::warn!(
+                        "The {:?} audio backend lacks {:?}, which need pipewire-pulse",
+                        backend_type,
+                        backend.features()
+                    );
+                }
+                return Ok((backend_type, backend));
+            }
+            Err(err) => {
+                tracing::warn!("Failed to connect to {:?}: {:?}", backend_type, err);
+                backend_type = backend_type.next().ok_or(err)?;
+            }
+        }
+    }
+}
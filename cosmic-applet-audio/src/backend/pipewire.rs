@@ -0,0 +1,544 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    io::Cursor,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use libpulse_binding::{
+    channelmap::{Map as ChannelMap, MapDef},
+    volume::{ChannelVolumes, Volume, VolumeLinear},
+};
+use pipewire::{
+    context::Context,
+    core::{self, Core, PW_ID_CORE},
+    main_loop::MainLoop,
+    metadata::{Metadata, MetadataListener},
+    node::{Node, NodeListener},
+    registry::{self, GlobalObject, Registry},
+    spa::{
+        param::ParamType,
+        pod::{
+            deserialize::PodDeserializer, serialize::PodSerializer, Object, Pod, Property, Value,
+            ValueArray,
+        },
+        sys as spa_sys,
+        utils::dict::DictRef,
+    },
+    types::ObjectType,
+};
+
+use crate::pulse::DeviceInfo;
+
+use super::{Backend, Change, Error, Result};
+
+// How long to wait for the server to answer before considering it gone
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NodeKind {
+    Sink,
+    Source,
+}
+
+impl NodeKind {
+    fn change(self) -> Change {
+        match self {
+            Self::Sink => Change::Sinks,
+            Self::Source => Change::Sources,
+        }
+    }
+}
+
+struct AudioNode {
+    kind: NodeKind,
+    proxy: Node,
+    _listener: NodeListener,
+    name: Option<String>,
+    description: Option<String>,
+    /// Linear channel volumes, as PipeWire stores them.
+    volumes: Vec<f32>,
+    mute: bool,
+}
+
+impl AudioNode {
+    fn device_info(&self, id: u32) -> DeviceInfo {
+        let mut volume = ChannelVolumes::default();
+        volume.set(self.volumes.len().clamp(1, 32) as u8, Volume::NORMAL);
+        for (volume, linear) in volume.get_mut().iter_mut().zip(&self.volumes) {
+            *volume = Volume::from(VolumeLinear(f64::from(*linear)));
+        }
+        // Positions aren't read from the node, so guess them from the count
+        let mut channel_map = ChannelMap::default();
+        channel_map.init_auto(u32::from(volume.len()), MapDef::Default);
+
+        DeviceInfo {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            volume,
+            channel_map,
+            mute: self.mute,
+            index: id,
+            ports: Vec::new(),
+            active_port: None,
+        }
+    }
+}
+
+/// Objects of the PipeWire graph the applet cares about, kept up to date by
+/// the registry, node and metadata listeners.
+#[derive(Default)]
+struct Graph {
+    nodes: HashMap<u32, AudioNode>,
+    metadata: Option<(Metadata, MetadataListener)>,
+    default_sink: Option<String>,
+    default_source: Option<String>,
+    changes: Vec<Change>,
+}
+
+impl Graph {
+    fn changed(&mut self, change: Change) {
+        if !self.changes.contains(&change) {
+            self.changes.push(change);
+        }
+    }
+
+    fn find(&self, kind: NodeKind, name: &str) -> Option<&AudioNode> {
+        self.nodes
+            .values()
+            .find(|node| node.kind == kind && node.name.as_deref() == Some(name))
+    }
+
+    fn default_device(&self, kind: NodeKind) -> Result<DeviceInfo> {
+        let name = match kind {
+            NodeKind::Sink => self.default_sink.as_deref(),
+            NodeKind::Source => self.default_source.as_deref(),
+        };
+        name.and_then(|name| {
+            self.nodes
+                .iter()
+                .find(|(_, node)| node.kind == kind && node.name.as_deref() == Some(name))
+        })
+        .map(|(id, node)| node.device_info(*id))
+        .ok_or(Error::Misc(format!("no default {:?}", kind)))
+    }
+
+    fn devices(&self, kind: NodeKind) -> Vec<DeviceInfo> {
+        let mut devices: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.kind == kind)
+            .map(|(id, node)| node.device_info(*id))
+            .collect();
+        devices.sort_by_key(|device| device.index);
+        devices
+    }
+}
+
+/// A connection to PipeWire through its native protocol, for systems without
+/// pipewire-pulse.
+///
+/// Only devices are supported; streams, cards and ports are left to the
+/// PulseAudio backend.
+pub struct PipeWireServer {
+    // Proxies and listeners have to be dropped before the core
+    graph: Rc<RefCell<Graph>>,
+    _registry_listener: registry::Listener,
+    _registry: Rc<Registry>,
+    _core_listener: core::Listener,
+    core: Core,
+    _context: Context,
+    mainloop: MainLoop,
+    error: Rc<Cell<bool>>,
+}
+
+fn connect_error(err: pipewire::Error) -> Error {
+    Error::Connect(err.to_string())
+}
+
+impl PipeWireServer {
+    pub fn connect() -> Result<Self> {
+        pipewire::init();
+
+        let mainloop = MainLoop::new(None).map_err(connect_error)?;
+        let context = Context::new(&mainloop).map_err(connect_error)?;
+        let core = context.connect(None).map_err(connect_error)?;
+        let registry = Rc::new(core.get_registry().map_err(connect_error)?);
+        let graph = Rc::new(RefCell::new(Graph::default()));
+        let error = Rc::new(Cell::new(false));
+
+        let core_error = error.clone();
+        let core_listener = core
+            .add_listener_local()
+            .error(move |id, _seq, res, message| {
+                tracing::error!("PipeWire error on object {}: {} ({})", id, message, res);
+                if id == PW_ID_CORE {
+                    core_error.set(true);
+                }
+            })
+            .register();
+
+        let registry_weak = Rc::downgrade(&registry);
+        let graph_weak = Rc::downgrade(&graph);
+        let removed_graph = Rc::downgrade(&graph);
+        let registry_listener = registry
+            .add_listener_local()
+            .global(move |global| {
+                if let (Some(registry), Some(graph)) =
+                    (registry_weak.upgrade(), graph_weak.upgrade())
+                {
+                    add_global(&registry, &graph, global);
+                }
+            })
+            .global_remove(move |id| {
+                let Some(graph) = removed_graph.upgrade() else {
+                    return;
+                };
+                let mut graph = graph.borrow_mut();
+                if let Some(node) = graph.nodes.remove(&id) {
+                    graph.changed(node.kind.change());
+                }
+            })
+            .register();
+
+        let server = Self {
+            graph,
+            _registry_listener: registry_listener,
+            _registry: registry,
+            _core_listener: core_listener,
+            core,
+            _context: context,
+            mainloop,
+            error,
+        };
+
+        // The first roundtrip lists the globals, the second one waits for
+        // the info and params of the nodes bound while listing them.
+        server.roundtrip()?;
+        server.roundtrip()?;
+
+        Ok(server)
+    }
+
+    /// Wait until the server has handled every request sent so far.
+    fn roundtrip(&self) -> Result<()> {
+        let pending = self
+            .core
+            .sync(0)
+            .map_err(|err| Error::Misc(err.to_string()))?;
+
+        let done = Rc::new(Cell::new(false));
+        let done_ref = done.clone();
+        let _listener = self
+            .core
+            .add_listener_local()
+            .done(move |id, seq| {
+                if id == PW_ID_CORE && seq == pending {
+                    done_ref.set(true);
+                }
+            })
+            .register();
+
+        let deadline = Instant::now() + TIMEOUT;
+        while !done.get() {
+            if self.error.get() || Instant::now() > deadline {
+                return Err(Error::Disconnected);
+            }
+            if self.mainloop.loop_().iterate(Duration::from_millis(100)) < 0 {
+                return Err(Error::Disconnected);
+            }
+        }
+        Ok(())
+    }
+
+    fn set_props(&self, kind: NodeKind, name: &str, properties: Vec<Property>) {
+        {
+            let graph = self.graph.borrow();
+            let Some(node) = graph.find(kind, name) else {
+                return;
+            };
+
+            let object = Value::Object(Object {
+                type_: spa_sys::SPA_TYPE_OBJECT_Props,
+                id: spa_sys::SPA_PARAM_Props,
+                properties,
+            });
+            let bytes = match PodSerializer::serialize(Cursor::new(Vec::new()), &object) {
+                Ok((cursor, _)) => cursor.into_inner(),
+                Err(err) => {
+                    tracing::error!("Failed to serialize props: {:?}", err);
+                    return;
+                }
+            };
+            if let Some(pod) = Pod::from_bytes(&bytes) {
+                node.proxy.set_param(ParamType::Props, 0, pod);
+            }
+        }
+
+        if let Err(err) = self.roundtrip() {
+            tracing::error!("Failed to set props of {}: {:?}", name, err);
+        }
+    }
+
+    fn set_volume(&self, kind: NodeKind, name: &str, volume: &ChannelVolumes) {
+        let volumes = volume
+            .get()
+            .iter()
+            .map(|volume| VolumeLinear::from(*volume).0 as f32)
+            .collect();
+        self.set_props(
+            kind,
+            name,
+            vec![
+                Property::new(
+                    spa_sys::SPA_PROP_channelVolumes,
+                    Value::ValueArray(ValueArray::Float(volumes)),
+                ),
+                Property::new(spa_sys::SPA_PROP_mute, Value::Bool(volume.is_muted())),
+            ],
+        );
+    }
+
+    fn set_mute(&self, kind: NodeKind, name: &str, mute: bool) {
+        self.set_props(
+            kind,
+            name,
+            vec![Property::new(spa_sys::SPA_PROP_mute, Value::Bool(mute))],
+        );
+    }
+
+    fn set_default(&self, kind: NodeKind, name: &str) -> bool {
+        {
+            let graph = self.graph.borrow();
+            if graph.find(kind, name).is_none() {
+                return false;
+            }
+            let Some((metadata, _)) = graph.metadata.as_ref() else {
+                return false;
+            };
+
+            let key = match kind {
+                NodeKind::Sink => "default.configured.audio.sink",
+                NodeKind::Source => "default.configured.audio.source",
+            };
+            let value = format!("{{ \"name\": \"{}\" }}", name);
+            metadata.set_property(0, key, Some("Spa:String:JSON"), Some(&value));
+        }
+
+        self.roundtrip().is_ok()
+    }
+}
+
+// Track audio devices and the default metadata as they show up
+fn add_global(registry: &Registry, graph: &Rc<RefCell<Graph>>, global: &GlobalObject<&DictRef>) {
+    let Some(props) = global.props else {
+        return;
+    };
+
+    match global.type_ {
+        ObjectType::Node => {
+            let kind = match props.get("media.class") {
+                Some("Audio/Sink") => NodeKind::Sink,
+                Some(class) if class.starts_with("Audio/Source") => NodeKind::Source,
+                _ => return,
+            };
+            let proxy: Node = match registry.bind(global) {
+                Ok(proxy) => proxy,
+                Err(err) => {
+                    tracing::error!("Failed to bind node {}: {}", global.id, err);
+                    return;
+                }
+            };
+
+            let id = global.id;
+            let info_graph = Rc::downgrade(graph);
+            let param_graph = Rc::downgrade(graph);
+            let listener = proxy
+                .add_listener_local()
+                .info(move |info| {
+                    let Some(graph) = info_graph.upgrade() else {
+                        return;
+                    };
+                    let mut graph = graph.borrow_mut();
+                    let Some(node) = graph.nodes.get_mut(&id) else {
+                        return;
+                    };
+                    if let Some(props) = info.props() {
+                        node.name = props.get("node.name").map(str::to_string);
+                        node.description = node_description(props);
+                    }
+                    let change = node.kind.change();
+                    graph.changed(change);
+                })
+                .param(move |_seq, param_type, _index, _next, param| {
+                    if param_type != ParamType::Props {
+                        return;
+                    }
+                    let (Some(param), Some(graph)) = (param, param_graph.upgrade()) else {
+                        return;
+                    };
+                    let mut graph = graph.borrow_mut();
+                    let Some(node) = graph.nodes.get_mut(&id) else {
+                        return;
+                    };
+                    if parse_props(param, &mut node.volumes, &mut node.mute) {
+                        let change = node.kind.change();
+                        graph.changed(change);
+                    }
+                })
+                .register();
+            proxy.subscribe_params(&[ParamType::Props]);
+
+            let mut graph = graph.borrow_mut();
+            graph.nodes.insert(
+                id,
+                AudioNode {
+                    kind,
+                    proxy,
+                    _listener: listener,
+                    name: props.get("node.name").map(str::to_string),
+                    description: node_description(props),
+                    volumes: Vec::new(),
+                    mute: false,
+                },
+            );
+            graph.changed(kind.change());
+        }
+        ObjectType::Metadata => {
+            if props.get("metadata.name") != Some("default") {
+                return;
+            }
+            let metadata: Metadata = match registry.bind(global) {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    tracing::error!("Failed to bind default metadata: {}", err);
+                    return;
+                }
+            };
+
+            let graph_weak = Rc::downgrade(graph);
+            let listener = metadata
+                .add_listener_local()
+                .property(move |_subject, key, _type, value| {
+                    let Some(graph) = graph_weak.upgrade() else {
+                        return 0;
+                    };
+                    let mut graph = graph.borrow_mut();
+                    let name = value.and_then(json_name);
+                    match key {
+                        Some("default.audio.sink") => graph.default_sink = name,
+                        Some("default.audio.source") => graph.default_source = name,
+                        // Every property was removed
+                        None => {
+                            graph.default_sink = None;
+                            graph.default_source = None;
+                        }
+                        _ => return 0,
+                    }
+                    graph.changed(Change::Server);
+                    0
+                })
+                .register();
+
+            graph.borrow_mut().metadata = Some((metadata, listener));
+        }
+        _ => {}
+    }
+}
+
+fn node_description(props: &DictRef) -> Option<String> {
+    props
+        .get("node.description")
+        .or_else(|| props.get("node.nick"))
+        .map(str::to_string)
+}
+
+// Read channel volumes and mute state from a `Props` param
+fn parse_props(param: &Pod, volumes: &mut Vec<f32>, mute: &mut bool) -> bool {
+    let Ok((_, Value::Object(object))) = PodDeserializer::deserialize_any_from(param.as_bytes())
+    else {
+        return false;
+    };
+
+    for property in object.properties {
+        match (property.key, property.value) {
+            (spa_sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(values))) => {
+                *volumes = values;
+            }
+            (spa_sys::SPA_PROP_mute, Value::Bool(value)) => *mute = value,
+            _ => {}
+        }
+    }
+    true
+}
+
+// Metadata values for default devices look like `{ "name": "alsa_output..." }`
+fn json_name(value: &str) -> Option<String> {
+    let (_, rest) = value.split_once("\"name\"")?;
+    let (_, rest) = rest.split_once('"')?;
+    let (name, _) = rest.split_once('"')?;
+    Some(name.to_string())
+}
+
+impl Backend for PipeWireServer {
+    fn is_connected(&mut self) -> bool {
+        !self.error.get() && self.roundtrip().is_ok()
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<Change>> {
+        while self.mainloop.loop_().iterate(Duration::ZERO) > 0 {}
+        if self.error.get() {
+            return Err(Error::Disconnected);
+        }
+        Ok(std::mem::take(&mut self.graph.borrow_mut().changes))
+    }
+
+    fn get_sinks(&mut self) -> Result<Vec<DeviceInfo>> {
+        self.roundtrip()?;
+        Ok(self.graph.borrow().devices(NodeKind::Sink))
+    }
+
+    fn get_sources(&mut self) -> Result<Vec<DeviceInfo>> {
+        self.roundtrip()?;
+        Ok(self.graph.borrow().devices(NodeKind::Source))
+    }
+
+    fn get_default_sink(&mut self) -> Result<DeviceInfo> {
+        self.roundtrip()?;
+        self.graph.borrow().default_device(NodeKind::Sink)
+    }
+
+    fn get_default_source(&mut self) -> Result<DeviceInfo> {
+        self.roundtrip()?;
+        self.graph.borrow().default_device(NodeKind::Source)
+    }
+
+    // The session manager moves streams that follow the default on its own
+    fn set_default_sink(&mut self, name: &str) -> bool {
+        self.set_default(NodeKind::Sink, name)
+    }
+
+    fn set_default_source(&mut self, name: &str) -> bool {
+        self.set_default(NodeKind::Source, name)
+    }
+
+    fn set_sink_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
+        self.set_volume(NodeKind::Sink, name, volume);
+    }
+
+    fn set_source_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
+        self.set_volume(NodeKind::Source, name, volume);
+    }
+
+    fn set_sink_mute_by_name(&mut self, name: &str, mute: bool) {
+        self.set_mute(NodeKind::Sink, name, mute);
+    }
+
+    fn set_source_mute_by_name(&mut self, name: &str, mute: bool) {
+        self.set_mute(NodeKind::Source, name, mute);
+    }
+}
@@ -2,10 +2,33 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
 #[version = 1]
 pub struct AudioAppletConfig {
     pub show_media_controls_in_top_panel: bool,
+    /// Bus name of the media player last picked in the popup, without the
+    /// suffix of its instance.
+    pub preferred_player: Option<String>,
+    /// Highest output volume in percent, above 100 to allow amplification.
+    pub max_volume: u32,
+    /// Names given by the user to devices, by device name.
+    pub device_aliases: BTreeMap<String, String>,
+    /// Devices left out of the device lists.
+    pub hidden_devices: Vec<String>,
+}
+
+impl Default for AudioAppletConfig {
+    fn default() -> Self {
+        Self {
+            show_media_controls_in_top_panel: false,
+            preferred_player: None,
+            max_volume: 100,
+            device_aliases: BTreeMap::new(),
+            hidden_devices: Vec::new(),
+        }
+    }
 }
@@ -0,0 +1,213 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Session bus interface to control the applet from volume keys and scripts.
+//!
+//! Only one applet instance serves the interface. Other instances, such as
+//! those on other panels, leave it to the first one and keep working without.
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, SinkExt},
+};
+use tokio::sync::mpsc;
+use zbus::{
+    fdo::{RequestNameFlags, RequestNameReply},
+    interface, Connection, SignalContext,
+};
+
+const NAME: &str = "com.system76.CosmicAppletAudio";
+const PATH: &str = "/com/system76/CosmicAppletAudio";
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The interface is served on this connection.
+    Ready(Connection),
+    Request(Request),
+}
+
+/// A call made on the interface.
+#[derive(Debug, Clone)]
+pub enum Request {
+    /// Raise the output volume by a step, in percent.
+    VolumeUp(u32),
+    /// Lower the output volume by a step, in percent.
+    VolumeDown(u32),
+    ToggleOutputMute,
+    ToggleInputMute,
+    SetDefaultOutput(String),
+    SetDefaultInput(String),
+    PlayPause,
+    Next,
+    Previous,
+}
+
+/// What the applet shows for a device, sent along with change signals.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceState {
+    pub name: String,
+    pub volume: u32,
+    pub mute: bool,
+}
+
+/// What the applet shows for the media player.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaState {
+    pub player: String,
+    pub status: String,
+    pub title: String,
+}
+
+struct AudioControl {
+    requests: mpsc::UnboundedSender<Request>,
+}
+
+impl AudioControl {
+    fn send(&self, request: Request) {
+        if let Err(err) = self.requests.send(request) {
+            tracing::error!("Failed to forward D-Bus request: {}", err);
+        }
+    }
+}
+
+#[interface(name = "com.system76.CosmicAppletAudio")]
+impl AudioControl {
+    fn volume_up(&self, step: u32) {
+        self.send(Request::VolumeUp(step));
+    }
+
+    fn volume_down(&self, step: u32) {
+        self.send(Request::VolumeDown(step));
+    }
+
+    fn toggle_output_mute(&self) {
+        self.send(Request::ToggleOutputMute);
+    }
+
+    fn toggle_input_mute(&self) {
+        self.send(Request::ToggleInputMute);
+    }
+
+    fn set_default_output(&self, name: String) {
+        self.send(Request::SetDefaultOutput(name));
+    }
+
+    fn set_default_input(&self, name: String) {
+        self.send(Request::SetDefaultInput(name));
+    }
+
+    fn play_pause(&self) {
+        self.send(Request::PlayPause);
+    }
+
+    fn next(&self) {
+        self.send(Request::Next);
+    }
+
+    fn previous(&self) {
+        self.send(Request::Previous);
+    }
+
+    #[zbus(signal)]
+    async fn output_changed(
+        ctxt: &SignalContext<'_>,
+        name: &str,
+        volume: u32,
+        mute: bool,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn input_changed(
+        ctxt: &SignalContext<'_>,
+        name: &str,
+        volume: u32,
+        mute: bool,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn media_changed(
+        ctxt: &SignalContext<'_>,
+        player: &str,
+        status: &str,
+        title: &str,
+    ) -> zbus::Result<()>;
+}
+
+pub fn subscription() -> iced::Subscription<Event> {
+    struct SomeWorker;
+
+    subscription::channel(
+        std::any::TypeId::of::<SomeWorker>(),
+        50,
+        |mut output| async move {
+            let (requests, mut requests_recv) = mpsc::unbounded_channel();
+            match serve(requests).await {
+                Ok(Some(conn)) => {
+                    _ = output.send(Event::Ready(conn)).await;
+                    while let Some(request) = requests_recv.recv().await {
+                        _ = output.send(Event::Request(request)).await;
+                    }
+                }
+                Ok(None) => {
+                    tracing::warn!(
+                        "{} is owned by another applet instance, not serving it",
+                        NAME
+                    );
+                }
+                Err(err) => {
+                    tracing::error!("Failed to serve audio control interface: {}", err);
+                }
+            }
+            futures::future::pending().await
+        },
+    )
+}
+
+// Serve the interface, unless another instance owns the name already
+async fn serve(requests: mpsc::UnboundedSender<Request>) -> zbus::Result<Option<Connection>> {
+    let conn = zbus::connection::Builder::session()?
+        .serve_at(PATH, AudioControl { requests })?
+        .build()
+        .await?;
+    match conn
+        .request_name_with_flags(NAME, RequestNameFlags::DoNotQueue.into())
+        .await?
+    {
+        RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner => Ok(Some(conn)),
+        RequestNameReply::Exists | RequestNameReply::InQueue => Ok(None),
+    }
+}
+
+pub async fn output_changed(conn: Connection, state: DeviceState) {
+    let res = match SignalContext::new(&conn, PATH) {
+        Ok(ctxt) => {
+            AudioControl::output_changed(&ctxt, &state.name, state.volume, state.mute).await
+        }
+        Err(err) => Err(err),
+    };
+    if let Err(err) = res {
+        tracing::error!("Failed to emit OutputChanged: {}", err);
+    }
+}
+
+pub async fn input_changed(conn: Connection, state: DeviceState) {
+    let res = match SignalContext::new(&conn, PATH) {
+        Ok(ctxt) => AudioControl::input_changed(&ctxt, &state.name, state.volume, state.mute).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = res {
+        tracing::error!("Failed to emit InputChanged: {}", err);
+    }
+}
+
+pub async fn media_changed(conn: Connection, state: MediaState) {
+    let res = match SignalContext::new(&conn, PATH) {
+        Ok(ctxt) => {
+            AudioControl::media_changed(&ctxt, &state.player, &state.status, &state.title).await
+        }
+        Err(err) => Err(err),
+    };
+    if let Err(err) = res {
+        tracing::error!("Failed to emit MediaChanged: {}", err);
+    }
+}
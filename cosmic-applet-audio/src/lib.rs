@@ -6,7 +6,11 @@ mod mouse_area;
 
 use std::time::Duration;
 
-use crate::{localize::localize, pulse::DeviceInfo};
+use crate::{
+    backend::Features,
+    localize::localize,
+    pulse::{CardInfo, DeviceInfo, PortInfo, StreamInfo},
+};
 use config::AudioAppletConfig;
 use cosmic::{
     app::Command,
@@ -24,7 +28,7 @@ use cosmic::{
     },
     iced_runtime::core::alignment::Horizontal,
     iced_style::application,
-    widget::{button, divider, horizontal_space, icon, text, Column, Row},
+    widget::{button, divider, horizontal_space, icon, text, text_input, Column, Row},
     Element, Renderer, Theme,
 };
 use cosmic_settings_subscriptions::pulse as sub_pulse;
@@ -33,11 +37,13 @@ use iced::{
     wayland::popup::{destroy_popup, get_popup},
     widget::container,
 };
-use libpulse_binding::volume::Volume;
+use libpulse_binding::{def::PortAvailable, volume::Volume};
 use mpris2_zbus::player::PlaybackStatus;
-use mpris_subscription::{MprisRequest, MprisUpdate};
+use mpris_subscription::{LoopStatus, MprisRequest, MprisUpdate};
 
+mod backend;
 mod config;
+mod dbus;
 mod mpris_subscription;
 mod pulse;
 
@@ -48,9 +54,17 @@ const GO_NEXT: &str = "media-skip-forward-symbolic";
 const PAUSE: &str = "media-playback-pause-symbolic";
 const PLAY: &str = "media-playback-start-symbolic";
 
+/// Choices for the highest output volume, in percent.
+const MAX_VOLUMES: [u32; 3] = [100, 125, 150];
+/// Marks the point on boosted sliders past which audio is amplified.
+static NORMAL_VOLUME_MARK: [f64; 1] = [100.];
+
 pub fn run() -> cosmic::iced::Result {
     localize();
-    cosmic::applet::run::<Audio>(true, ())
+    let result = cosmic::applet::run::<Audio>(true, ());
+    // the combined sink would outlive the applet otherwise
+    pulse::unload_combined_sink();
+    result
 }
 
 #[derive(Default)]
@@ -67,11 +81,32 @@ pub struct Audio {
     current_input: Option<DeviceInfo>,
     outputs: Vec<DeviceInfo>,
     inputs: Vec<DeviceInfo>,
+    sink_inputs: Vec<StreamInfo>,
+    source_outputs: Vec<StreamInfo>,
+    cards: Vec<CardInfo>,
     pulse_state: PulseState,
     popup: Option<window::Id>,
     timeline: Timeline,
     config: AudioAppletConfig,
     player_status: Option<mpris_subscription::PlayerStatus>,
+    players: Vec<mpris_subscription::PlayerStatus>,
+    /// Position in seconds while the seek bar is being dragged.
+    seek_position: Option<f64>,
+    /// Volume in percent while the player's volume slider is being dragged.
+    player_volume: Option<f64>,
+    /// Whether the applet loaded a sink playing to several outputs.
+    combined_sink: bool,
+    combine_selection: Vec<String>,
+    /// Alias being typed for a device, saved once submitted or when the
+    /// editor closes.
+    alias_edit: Option<(String, String)>,
+    /// What the sound server backend supports.
+    features: Features,
+    dbus: Option<zbus::Connection>,
+    /// State last sent in D-Bus signals, to only signal changes.
+    dbus_output: Option<dbus::DeviceState>,
+    dbus_input: Option<dbus::DeviceState>,
+    dbus_media: Option<dbus::MediaState>,
     token_tx: Option<calloop::channel::Sender<TokenRequest>>,
 }
 
@@ -79,8 +114,9 @@ impl Audio {
     fn update_output(&mut self, output: Option<DeviceInfo>) {
         self.current_output = output;
 
+        // The loudest channel, so that the balance is kept when it is scaled
         if let Some(device) = self.current_output.as_ref() {
-            self.output_volume = volume_to_percent(device.volume.avg());
+            self.output_volume = volume_to_percent(device.volume.max());
             self.output_volume_text = format!("{}%", self.output_volume.round());
         }
     }
@@ -130,6 +166,21 @@ enum IsOpen {
     None,
     Output,
     Input,
+    Stream(StreamKind, u32),
+    Card(u32),
+    Players,
+    Channels,
+    MaxVolume,
+    DeviceEditor,
+    Combine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    /// An application playing to a sink.
+    Playback,
+    /// An application recording from a source.
+    Recording,
 }
 
 #[derive(Debug, Clone)]
@@ -141,11 +192,34 @@ pub enum Message {
     SetInputVolume(f64),
     SetOutputMute(bool),
     SetInputMute(bool),
+    SetSourceMute(String, bool),
+    SetStreamVolume(StreamKind, u32, f64),
+    SetStreamMute(StreamKind, u32, bool),
+    StreamToggle(StreamKind, u32),
+    StreamDeviceChanged(StreamKind, u32, String),
+    CardToggle(u32),
+    CardProfileChanged(u32, String),
     OutputToggle,
     InputToggle,
     OutputChanged(String),
     InputChanged(String),
+    OutputPortChanged(String, String),
+    ChannelsToggle,
+    SetOutputBalance(f32),
+    SetOutputChannelVolume(usize, f64),
+    DeviceEditorToggle,
+    CombineToggle,
+    CombineSelect(String),
+    CombineOutputs,
+    UncombineOutputs,
+    SetDeviceAlias(String, String),
+    SaveDeviceAlias,
+    SetDeviceHidden(String, bool),
+    MaxVolumeToggle,
+    MaxVolumeChanged(u32),
+    InputPortChanged(String, String),
     Pulse(pulse::Event),
+    Reconnect,
     TogglePopup,
     CloseRequested(window::Id),
     ToggleMediaControlsInTopPanel(chain::Toggler, bool),
@@ -153,9 +227,15 @@ pub enum Message {
     ConfigChanged(AudioAppletConfig),
     Mpris(mpris_subscription::MprisUpdate),
     MprisRequest(MprisRequest),
+    PlayersToggle,
+    PlayerChanged(String),
+    MprisSeek(f64),
+    SetPlayerVolume(f64),
+    MprisTick,
     Token(TokenUpdate),
     OpenSettings,
     PulseSub(sub_pulse::Event),
+    Dbus(dbus::Event),
 }
 
 impl Audio {
@@ -217,6 +297,158 @@ impl Audio {
         }
     }
 
+    // The player picked in the popup if it is still around, otherwise the
+    // one that is most likely to be wanted
+    fn update_player_status(&mut self) {
+        self.player_status = self
+            .config
+            .preferred_player
+            .as_ref()
+            .and_then(|id| self.players.iter().find(|p| p.id() == id.as_str()))
+            .or_else(|| mpris_subscription::find_active(&self.players))
+            .cloned();
+    }
+
+    // Keep the alias that was being typed
+    fn save_device_alias(&mut self) {
+        let Some((name, alias)) = self.alias_edit.take() else {
+            return;
+        };
+        if alias.is_empty() {
+            self.config.device_aliases.remove(&name);
+        } else {
+            self.config.device_aliases.insert(name, alias);
+        }
+        self.write_config();
+    }
+
+    fn write_config(&self) {
+        if let Ok(helper) =
+            cosmic::cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION)
+        {
+            if let Err(err) = self.config.write_entry(&helper) {
+                tracing::error!(?err, "Error writing config");
+            }
+        }
+    }
+
+    // Seek bar, or a plain progress bar if the player can't seek
+    fn track_progress<'a>(
+        &self,
+        s: &'a mpris_subscription::PlayerStatus,
+    ) -> Option<Element<'a, Message>> {
+        let length = s.length?.as_secs_f64();
+        let position = self
+            .seek_position
+            .unwrap_or_else(|| s.position().as_secs_f64())
+            .min(length);
+
+        let bar: Element<_> = if s.can_seek {
+            slider(0.0..=length, position, Message::MprisSeek)
+                .on_release(Message::MprisRequest(MprisRequest::SetPosition(
+                    Duration::from_secs_f64(position),
+                )))
+                .width(Length::FillPortion(5))
+                .into()
+        } else {
+            widget::progress_bar(0.0..=length as f32, position as f32)
+                .height(4)
+                .width(Length::FillPortion(5))
+                .into()
+        };
+
+        Some(
+            padded_control(
+                row![
+                    text::caption(format_time(position)),
+                    bar,
+                    text::caption(format_time(length)),
+                ]
+                .spacing(12)
+                .align_items(Alignment::Center),
+            )
+            .into(),
+        )
+    }
+
+    // Shuffle, loop and volume, for players that allow changing them
+    fn player_options<'a>(
+        &self,
+        s: &'a mpris_subscription::PlayerStatus,
+    ) -> Option<Element<'a, Message>> {
+        if !s.can_control {
+            return None;
+        }
+
+        let mut elements: Vec<Element<_>> = Vec::with_capacity(3);
+        if let Some(shuffle) = s.shuffle {
+            elements.push(
+                button::icon(
+                    icon::from_name(if shuffle {
+                        "media-playlist-shuffle-symbolic"
+                    } else {
+                        "media-playlist-consecutive-symbolic"
+                    })
+                    .size(16)
+                    .symbolic(true),
+                )
+                .extra_small()
+                .style(cosmic::theme::Button::AppletIcon)
+                .on_press(Message::MprisRequest(MprisRequest::SetShuffle(!shuffle)))
+                .into(),
+            );
+        }
+        if let Some(loop_status) = s.loop_status {
+            elements.push(
+                button::icon(
+                    icon::from_name(match loop_status {
+                        LoopStatus::None => "media-playlist-no-repeat-symbolic",
+                        LoopStatus::Track => "media-playlist-repeat-song-symbolic",
+                        LoopStatus::Playlist => "media-playlist-repeat-symbolic",
+                    })
+                    .size(16)
+                    .symbolic(true),
+                )
+                .extra_small()
+                .style(cosmic::theme::Button::AppletIcon)
+                .on_press(Message::MprisRequest(MprisRequest::SetLoopStatus(
+                    loop_status.next(),
+                )))
+                .into(),
+            );
+        }
+        if let Some(volume) = s.volume {
+            let volume = self.player_volume.unwrap_or(volume * 100.);
+            elements.push(
+                icon::from_name("audio-volume-high-symbolic")
+                    .size(16)
+                    .symbolic(true)
+                    .icon()
+                    .into(),
+            );
+            elements.push(
+                slider(0.0..=100.0, volume, Message::SetPlayerVolume)
+                    .on_release(Message::MprisRequest(MprisRequest::SetVolume(
+                        volume / 100.,
+                    )))
+                    .width(Length::Fill)
+                    .into(),
+            );
+        }
+
+        if elements.is_empty() {
+            return None;
+        }
+        Some(
+            padded_control(
+                Row::with_children(elements)
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+            )
+            .into(),
+        )
+    }
+
     fn go_previous(&self, icon_size: u16) -> Option<Element<Message>> {
         self.player_status.as_ref().and_then(|s| {
             if s.can_go_previous {
@@ -269,6 +501,502 @@ impl Audio {
         })
     }
 
+    fn streams_mut(&mut self, kind: StreamKind) -> &mut Vec<StreamInfo> {
+        match kind {
+            StreamKind::Playback => &mut self.sink_inputs,
+            StreamKind::Recording => &mut self.source_outputs,
+        }
+    }
+
+    fn devices(&self, kind: StreamKind) -> &[DeviceInfo] {
+        match kind {
+            StreamKind::Playback => &self.outputs,
+            StreamKind::Recording => &self.inputs,
+        }
+    }
+
+    // Streams connected to a device listed in the popup, which leaves out
+    // applications recording from monitor sources
+    fn visible_streams(&self, kind: StreamKind) -> impl Iterator<Item = &StreamInfo> {
+        let streams = match kind {
+            StreamKind::Playback => &self.sink_inputs,
+            StreamKind::Recording => &self.source_outputs,
+        };
+        streams.iter().filter(move |s| {
+            kind == StreamKind::Playback || self.inputs.iter().any(|i| i.index == s.device)
+        })
+    }
+
+    // Sources that applications are recording from
+    fn recorded_sources(&self) -> Vec<&DeviceInfo> {
+        self.inputs
+            .iter()
+            .filter(|i| self.source_outputs.iter().any(|s| s.device == i.index))
+            .collect()
+    }
+
+    // Whether anything can currently hear the microphone
+    fn is_listening(&self) -> bool {
+        self.recorded_sources().iter().any(|source| {
+            !source.mute
+                && self
+                    .source_outputs
+                    .iter()
+                    .any(|s| s.device == source.index && !s.mute)
+        })
+    }
+
+    fn recorded_sources_view(&self) -> Element<Message> {
+        Column::with_children(self.recorded_sources().into_iter().map(|source| {
+            let name = source.name.clone().unwrap_or_default();
+            padded_control(
+                row![
+                    icon::from_name(if source.mute {
+                        "microphone-sensitivity-muted-symbolic"
+                    } else {
+                        "audio-input-microphone-symbolic"
+                    })
+                    .size(24)
+                    .symbolic(true)
+                    .icon(),
+                    text::body(self.device_name(source)).width(Length::Fill),
+                    button::text(if source.mute {
+                        fl!("unmute")
+                    } else {
+                        fl!("mute")
+                    })
+                    .on_press(Message::SetSourceMute(name, !source.mute)),
+                ]
+                .spacing(12)
+                .align_items(Alignment::Center),
+            )
+            .into()
+        }))
+        .into()
+    }
+
+    fn streams(&self, kind: StreamKind) -> Element<Message> {
+        let devices = self.devices(kind);
+
+        Column::with_children(self.visible_streams(kind).map(|stream| {
+            let index = stream.index;
+            let volume = volume_to_percent(stream.volume.avg());
+            let name = pretty_name(
+                stream
+                    .application_name
+                    .clone()
+                    .or_else(|| stream.media_name.clone()),
+            );
+            let device = devices
+                .iter()
+                .find(|d| d.index == stream.device)
+                .map(|d| self.device_name(d))
+                .unwrap_or_else(|| fl!("no-device"));
+
+            column![
+                revealer(
+                    self.is_open == IsOpen::Stream(kind, index),
+                    name,
+                    device,
+                    devices
+                        .iter()
+                        .filter(|d| !self.is_hidden(d))
+                        .map(|d| (d.name.clone().unwrap_or_default(), self.device_name(d)))
+                        .collect(),
+                    Message::StreamToggle(kind, index),
+                    move |name| Message::StreamDeviceChanged(kind, index, name),
+                ),
+                padded_control(
+                    row![
+                        icon::from_name(
+                            stream
+                                .icon_name
+                                .as_deref()
+                                .unwrap_or("application-x-executable")
+                        )
+                        .size(24)
+                        .icon(),
+                        slider(0.0..=100.0, volume, move |v| Message::SetStreamVolume(
+                            kind, index, v
+                        ))
+                        .width(Length::Fill),
+                        button::icon(
+                            icon::from_name(stream_icon_name(stream.mute, volume))
+                                .size(16)
+                                .symbolic(true),
+                        )
+                        .style(cosmic::theme::Button::Icon)
+                        .on_press(Message::SetStreamMute(
+                            kind,
+                            index,
+                            !stream.mute
+                        )),
+                    ]
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+                ),
+            ]
+            .into()
+        }))
+        .into()
+    }
+
+    // The user's alias for a device, or its description
+    fn device_name(&self, device: &DeviceInfo) -> String {
+        device
+            .name
+            .as_ref()
+            .and_then(|name| self.config.device_aliases.get(name))
+            .cloned()
+            .unwrap_or_else(|| pretty_name(device.description.clone()))
+    }
+
+    fn is_hidden(&self, device: &DeviceInfo) -> bool {
+        device
+            .name
+            .as_ref()
+            .is_some_and(|name| self.config.hidden_devices.contains(name))
+    }
+
+    fn port_label(&self, device: &DeviceInfo, port: &PortInfo) -> String {
+        let label = format!(
+            "{} - {}",
+            self.device_name(device),
+            pretty_name(port.description.clone())
+        );
+        if port.available == PortAvailable::No {
+            format!("{label} ({})", fl!("unplugged"))
+        } else {
+            label
+        }
+    }
+
+    // Name of a device, along with its active port if it has a choice of ports
+    fn device_label(&self, device: &DeviceInfo) -> String {
+        match device
+            .ports
+            .iter()
+            .find(|p| device.ports.len() > 1 && device.active_port.as_ref() == Some(&p.name))
+        {
+            Some(port) => self.port_label(device, port),
+            None => self.device_name(device),
+        }
+    }
+
+    // Devices to select from, with an entry for every port of devices that have
+    // several, e.g. speakers and headphones on the same sink. Hidden devices
+    // are left out, though they are still shown once they are selected.
+    fn device_options(&self, devices: &[DeviceInfo]) -> Vec<((String, Option<String>), String)> {
+        let mut options = Vec::with_capacity(devices.len());
+        for device in devices.iter().filter(|d| !self.is_hidden(d)) {
+            let name = device.name.clone().unwrap_or_default();
+            if device.ports.len() > 1 {
+                for port in &device.ports {
+                    options.push((
+                        (name.clone(), Some(port.name.clone())),
+                        self.port_label(device, port),
+                    ));
+                }
+            } else {
+                options.push(((name, None), self.device_name(device)));
+            }
+        }
+        options
+    }
+
+    // Outputs to pick for playing to all of them at once
+    fn combine_outputs(&self) -> Element<Message> {
+        let outputs: Vec<_> = self
+            .outputs
+            .iter()
+            .filter(|o| !self.is_hidden(o) && o.name.as_deref() != Some(pulse::COMBINED_SINK_NAME))
+            .collect();
+        if !self.features.combined_sink || outputs.len() < 2 && !self.combined_sink {
+            return column![].into();
+        }
+
+        let open = self.is_open == IsOpen::Combine;
+        let mut content = column![revealer_head(
+            open,
+            fl!("combine-outputs"),
+            if self.combined_sink {
+                fl!("combine-active")
+            } else {
+                fl!("combine-inactive")
+            },
+            Message::CombineToggle
+        )]
+        .width(Length::Fill);
+        if !open {
+            return content.into();
+        }
+
+        if self.combined_sink {
+            return content
+                .push(
+                    menu_button(text::body(fl!("uncombine")))
+                        .on_press(Message::UncombineOutputs)
+                        .width(Length::Fill)
+                        .padding([8, 48]),
+                )
+                .into();
+        }
+
+        for output in outputs {
+            let Some(name) = output.name.clone() else {
+                continue;
+            };
+            let selected = self.combine_selection.contains(&name);
+            content = content.push(
+                menu_button(
+                    row![
+                        icon::from_name(if selected {
+                            "checkbox-checked-symbolic"
+                        } else {
+                            "checkbox-symbolic"
+                        })
+                        .size(16)
+                        .icon(),
+                        text::body(self.device_name(output)),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Message::CombineSelect(name))
+                .width(Length::Fill)
+                .padding([8, 48]),
+            );
+        }
+        let mut combine = menu_button(text::body(fl!("combine")))
+            .width(Length::Fill)
+            .padding([8, 48]);
+        if self.combine_selection.len() > 1 {
+            combine = combine.on_press(Message::CombineOutputs);
+        }
+        content.push(combine).into()
+    }
+
+    // Rows to rename or hide every output and input
+    fn device_editor(&self) -> Element<Message> {
+        let open = self.is_open == IsOpen::DeviceEditor;
+        let mut content = column![revealer_head(
+            open,
+            fl!("edit-devices"),
+            fl!("edit-devices-description"),
+            Message::DeviceEditorToggle
+        )]
+        .width(Length::Fill);
+        if !open {
+            return content.into();
+        }
+
+        for device in self.outputs.iter().chain(&self.inputs) {
+            let Some(name) = device.name.clone() else {
+                continue;
+            };
+            let hidden = self.is_hidden(device);
+            let alias = match &self.alias_edit {
+                Some((edited, alias)) if *edited == name => alias.clone(),
+                _ => self
+                    .config
+                    .device_aliases
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_default(),
+            };
+            let input_name = name.clone();
+            content = content.push(padded_control(
+                row![
+                    text_input(pretty_name(device.description.clone()), alias)
+                        .on_input(move |alias| Message::SetDeviceAlias(input_name.clone(), alias))
+                        .on_submit(Message::SaveDeviceAlias)
+                        .width(Length::Fill),
+                    button::icon(
+                        icon::from_name(if hidden {
+                            "view-conceal-symbolic"
+                        } else {
+                            "view-reveal-symbolic"
+                        })
+                        .size(16)
+                        .symbolic(true),
+                    )
+                    .style(cosmic::theme::Button::Icon)
+                    .on_press(Message::SetDeviceHidden(name, !hidden)),
+                ]
+                .spacing(12)
+                .align_items(Alignment::Center),
+            ));
+        }
+        content.into()
+    }
+
+    fn max_volume(&self) -> f64 {
+        f64::from(self.config.max_volume.max(100))
+    }
+
+    // Output volume slider going up to the configured maximum, with a mark
+    // at 100% when it goes past it
+    fn boost_slider<'a>(
+        &self,
+        value: f64,
+        on_change: impl Fn(f64) -> Message + 'a,
+    ) -> widget::Slider<'a, f64, Message, Theme> {
+        let max_volume = self.max_volume();
+        let slider = slider(0.0..=max_volume, value, on_change);
+        if max_volume > 100. {
+            slider.breakpoints(&NORMAL_VOLUME_MARK)
+        } else {
+            slider
+        }
+    }
+
+    // Tell D-Bus listeners about changes to what the applet shows
+    fn emit_dbus_signals(&mut self) {
+        let Some(conn) = self.dbus.clone() else {
+            return;
+        };
+
+        let output = self.current_output.as_ref().map(|o| dbus::DeviceState {
+            name: o.name.clone().unwrap_or_default(),
+            volume: self.output_volume.round() as u32,
+            mute: o.mute,
+        });
+        if output != self.dbus_output {
+            self.dbus_output = output.clone();
+            if let Some(state) = output {
+                tokio::spawn(dbus::output_changed(conn.clone(), state));
+            }
+        }
+
+        let input = self.current_input.as_ref().map(|i| dbus::DeviceState {
+            name: i.name.clone().unwrap_or_default(),
+            volume: self.input_volume.round() as u32,
+            mute: i.mute,
+        });
+        if input != self.dbus_input {
+            self.dbus_input = input.clone();
+            if let Some(state) = input {
+                tokio::spawn(dbus::input_changed(conn.clone(), state));
+            }
+        }
+
+        let media = self.player_status.as_ref().map(|s| dbus::MediaState {
+            player: s.name().to_string(),
+            status: match s.status {
+                PlaybackStatus::Playing => "Playing",
+                PlaybackStatus::Paused => "Paused",
+                PlaybackStatus::Stopped => "Stopped",
+            }
+            .to_string(),
+            title: s.title.as_deref().unwrap_or_default().to_string(),
+        });
+        if media != self.dbus_media {
+            self.dbus_media = media.clone();
+            tokio::spawn(dbus::media_changed(conn, media.unwrap_or_default()));
+        }
+    }
+
+    // Bring the output volume down to a lowered maximum
+    fn clamp_output_volume(&mut self) {
+        let max_volume = self.max_volume();
+        if self.output_volume <= max_volume {
+            return;
+        }
+        self.output_volume = max_volume;
+        self.output_volume_text = format!("{}%", self.output_volume.round());
+        if let Some(output) = self.current_output.as_mut() {
+            output.volume.scale(percent_to_volume(max_volume));
+        }
+        self.send_output_volume();
+    }
+
+    fn send_output_volume(&mut self) {
+        if let PulseState::Connected(connection) = &mut self.pulse_state {
+            if let Some(device) = &self.current_output {
+                if let Some(name) = &device.name {
+                    connection.send(pulse::Message::SetSinkVolumeByName(
+                        name.clone(),
+                        device.volume,
+                    ))
+                }
+            }
+        }
+    }
+
+    // Send the output volume at most once in a while as a slider is dragged
+    fn debounce_output_volume(&mut self) -> Command<Message> {
+        if self.output_volume_debounce {
+            return Command::none();
+        }
+
+        self.output_volume_debounce = true;
+
+        cosmic::command::future(async move {
+            tokio::time::sleep(Duration::from_millis(64)).await;
+            Message::ApplyOutputVolume
+        })
+    }
+
+    // Balance of the selected output, and a slider per channel when there
+    // are more than two of them
+    fn output_channels(&self) -> Element<Message> {
+        let Some(output) = self.current_output.as_ref() else {
+            return column![].into();
+        };
+        let can_balance = output.channel_map.can_balance();
+        let multichannel = output.volume.len() > 2;
+        if !can_balance && !multichannel {
+            return column![].into();
+        }
+
+        let open = self.is_open == IsOpen::Channels;
+        let balance = output.volume.get_balance(&output.channel_map);
+        let mut content = column![revealer_head(
+            open,
+            fl!("balance"),
+            balance_label(balance),
+            Message::ChannelsToggle
+        )]
+        .width(Length::Fill);
+        if !open {
+            return content.into();
+        }
+
+        if can_balance {
+            content = content.push(padded_control(
+                row![
+                    text::caption(fl!("left")),
+                    slider(-1.0..=1.0, balance, Message::SetOutputBalance)
+                        .step(0.05)
+                        .on_release(Message::ApplyOutputVolume)
+                        .width(Length::Fill),
+                    text::caption(fl!("right")),
+                ]
+                .spacing(12)
+                .align_items(Alignment::Center),
+            ));
+        }
+        if multichannel {
+            let channels = output.channel_map.get().iter().zip(output.volume.get());
+            for (channel, (position, volume)) in channels.enumerate() {
+                content = content.push(padded_control(
+                    row![
+                        text::caption(position.to_pretty_string().unwrap_or_default())
+                            .width(Length::FillPortion(2)),
+                        self.boost_slider(volume_to_percent(*volume), move |v| {
+                            Message::SetOutputChannelVolume(channel, v)
+                        })
+                        .on_release(Message::ApplyOutputVolume)
+                        .width(Length::FillPortion(5)),
+                    ]
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+                ));
+            }
+        }
+        content.into()
+    }
+
     fn current_output_mute(&self) -> bool {
         self.current_output
             .as_ref()
@@ -324,6 +1052,10 @@ impl cosmic::Application for Audio {
             Message::Ignore => {}
             Message::TogglePopup => {
                 if let Some(p) = self.popup.take() {
+                    self.save_device_alias();
+                    if let Some(conn) = self.pulse_state.connection() {
+                        conn.send(pulse::Message::Watch(false));
+                    }
                     return destroy_popup(p);
                 } else {
                     if let Some(conn) = self.pulse_state.connection() {
@@ -347,10 +1079,14 @@ impl cosmic::Application for Audio {
                         .max_height(1080.0);
 
                     if let Some(conn) = self.pulse_state.connection() {
+                        conn.send(pulse::Message::Watch(true));
                         conn.send(pulse::Message::GetDefaultSink);
                         conn.send(pulse::Message::GetDefaultSource);
                         conn.send(pulse::Message::GetSinks);
                         conn.send(pulse::Message::GetSources);
+                        conn.send(pulse::Message::GetSinkInputs);
+                        conn.send(pulse::Message::GetSourceOutputs);
+                        conn.send(pulse::Message::GetCards);
                     }
 
                     return get_popup(popup_settings);
@@ -364,16 +1100,7 @@ impl cosmic::Application for Audio {
                 self.output_volume = vol;
                 self.output_volume_text = format!("{}%", self.output_volume.round());
 
-                if self.output_volume_debounce {
-                    return Command::none();
-                }
-
-                self.output_volume_debounce = true;
-
-                return cosmic::command::future(async move {
-                    tokio::time::sleep(Duration::from_millis(64)).await;
-                    Message::ApplyOutputVolume
-                });
+                return self.debounce_output_volume();
             }
             Message::SetInputVolume(vol) => {
                 if self.input_volume == vol {
@@ -397,21 +1124,108 @@ impl cosmic::Application for Audio {
             Message::ApplyOutputVolume => {
                 self.output_volume_debounce = false;
 
-                self.current_output.as_mut().map(|o| {
-                    o.volume
-                        .set(o.volume.len(), percent_to_volume(self.output_volume))
-                });
+                self.current_output
+                    .as_mut()
+                    .map(|o| o.volume.scale(percent_to_volume(self.output_volume)));
 
-                if let PulseState::Connected(connection) = &mut self.pulse_state {
-                    if let Some(device) = &self.current_output {
-                        if let Some(name) = &device.name {
-                            connection.send(pulse::Message::SetSinkVolumeByName(
-                                name.clone(),
-                                device.volume,
-                            ))
-                        }
+                self.send_output_volume();
+                self.emit_dbus_signals();
+            }
+            Message::CombineToggle => {
+                self.is_open = if self.is_open == IsOpen::Combine {
+                    IsOpen::None
+                } else {
+                    IsOpen::Combine
+                };
+            }
+            Message::CombineSelect(name) => {
+                if let Some(pos) = self.combine_selection.iter().position(|n| n == &name) {
+                    self.combine_selection.remove(pos);
+                } else {
+                    self.combine_selection.push(name);
+                }
+            }
+            Message::CombineOutputs => {
+                self.is_open = IsOpen::None;
+                let sinks = self
+                    .combine_selection
+                    .iter()
+                    .filter(|name| self.outputs.iter().any(|o| o.name.as_ref() == Some(*name)))
+                    .cloned()
+                    .collect();
+                if let Some(conn) = self.pulse_state.connection() {
+                    conn.send(pulse::Message::LoadCombinedSink(sinks));
+                }
+            }
+            Message::UncombineOutputs => {
+                self.is_open = IsOpen::None;
+                if let Some(conn) = self.pulse_state.connection() {
+                    conn.send(pulse::Message::UnloadCombinedSink);
+                }
+            }
+            Message::DeviceEditorToggle => {
+                self.is_open = if self.is_open == IsOpen::DeviceEditor {
+                    self.save_device_alias();
+                    IsOpen::None
+                } else {
+                    IsOpen::DeviceEditor
+                };
+            }
+            Message::SetDeviceAlias(name, alias) => {
+                if self
+                    .alias_edit
+                    .as_ref()
+                    .is_some_and(|(edited, _)| *edited != name)
+                {
+                    self.save_device_alias();
+                }
+                self.alias_edit = Some((name, alias));
+            }
+            Message::SaveDeviceAlias => self.save_device_alias(),
+            Message::SetDeviceHidden(name, hidden) => {
+                self.config.hidden_devices.retain(|n| n != &name);
+                if hidden {
+                    self.config.hidden_devices.push(name);
+                }
+                self.write_config();
+            }
+            Message::MaxVolumeToggle => {
+                self.is_open = if self.is_open == IsOpen::MaxVolume {
+                    IsOpen::None
+                } else {
+                    IsOpen::MaxVolume
+                };
+            }
+            Message::MaxVolumeChanged(max_volume) => {
+                self.is_open = IsOpen::None;
+                self.config.max_volume = max_volume;
+                self.write_config();
+                self.clamp_output_volume();
+                self.emit_dbus_signals();
+            }
+            Message::ChannelsToggle => {
+                self.is_open = if self.is_open == IsOpen::Channels {
+                    IsOpen::None
+                } else {
+                    IsOpen::Channels
+                };
+            }
+            Message::SetOutputBalance(balance) => {
+                if let Some(output) = self.current_output.as_mut() {
+                    let channel_map = output.channel_map;
+                    output.volume.set_balance(&channel_map, balance);
+                }
+                return self.debounce_output_volume();
+            }
+            Message::SetOutputChannelVolume(channel, volume) => {
+                if let Some(output) = self.current_output.as_mut() {
+                    if let Some(v) = output.volume.get_mut().get_mut(channel) {
+                        *v = percent_to_volume(volume);
                     }
+                    self.output_volume = volume_to_percent(output.volume.max());
+                    self.output_volume_text = format!("{}%", self.output_volume.round());
                 }
+                return self.debounce_output_volume();
             }
             Message::ApplyInputVolume => {
                 self.input_volume_debounce = false;
@@ -432,6 +1246,7 @@ impl cosmic::Application for Audio {
                         }
                     }
                 }
+                self.emit_dbus_signals();
             }
             Message::SetOutputMute(mute) => {
                 if let Some(output) = self.current_output.as_mut() {
@@ -445,6 +1260,7 @@ impl cosmic::Application for Audio {
                         }
                     }
                 }
+                self.emit_dbus_signals();
             }
             Message::SetInputMute(mute) => {
                 if let Some(input) = self.current_input.as_mut() {
@@ -460,6 +1276,83 @@ impl cosmic::Application for Audio {
                         }
                     }
                 }
+                self.emit_dbus_signals();
+            }
+            Message::SetSourceMute(name, mute) => {
+                for input in self.inputs.iter_mut().chain(self.current_input.as_mut()) {
+                    if input.name.as_ref() == Some(&name) {
+                        input.mute = mute;
+                    }
+                }
+                if let PulseState::Connected(connection) = &mut self.pulse_state {
+                    connection.send(pulse::Message::SetSourceMuteByName(name, mute));
+                }
+                self.emit_dbus_signals();
+            }
+            Message::SetStreamVolume(kind, index, vol) => {
+                let Some(stream) = self.streams_mut(kind).iter_mut().find(|s| s.index == index)
+                else {
+                    return Command::none();
+                };
+                stream
+                    .volume
+                    .set(stream.volume.len(), percent_to_volume(vol));
+                let volume = stream.volume;
+
+                if let PulseState::Connected(connection) = &mut self.pulse_state {
+                    connection.send(match kind {
+                        StreamKind::Playback => pulse::Message::SetSinkInputVolume(index, volume),
+                        StreamKind::Recording => {
+                            pulse::Message::SetSourceOutputVolume(index, volume)
+                        }
+                    });
+                }
+            }
+            Message::SetStreamMute(kind, index, mute) => {
+                let Some(stream) = self.streams_mut(kind).iter_mut().find(|s| s.index == index)
+                else {
+                    return Command::none();
+                };
+                stream.mute = mute;
+
+                if let PulseState::Connected(connection) = &mut self.pulse_state {
+                    connection.send(match kind {
+                        StreamKind::Playback => pulse::Message::SetSinkInputMute(index, mute),
+                        StreamKind::Recording => pulse::Message::SetSourceOutputMute(index, mute),
+                    });
+                }
+            }
+            Message::StreamToggle(kind, index) => {
+                self.is_open = if self.is_open == IsOpen::Stream(kind, index) {
+                    IsOpen::None
+                } else {
+                    IsOpen::Stream(kind, index)
+                }
+            }
+            Message::StreamDeviceChanged(kind, index, name) => {
+                self.is_open = IsOpen::None;
+                if let Some(conn) = self.pulse_state.connection() {
+                    conn.send(match kind {
+                        StreamKind::Playback => pulse::Message::MoveSinkInput(index, name),
+                        StreamKind::Recording => pulse::Message::MoveSourceOutput(index, name),
+                    });
+                }
+            }
+            Message::CardToggle(index) => {
+                self.is_open = if self.is_open == IsOpen::Card(index) {
+                    IsOpen::None
+                } else {
+                    if let Some(conn) = self.pulse_state.connection() {
+                        conn.send(pulse::Message::GetCards);
+                    }
+                    IsOpen::Card(index)
+                }
+            }
+            Message::CardProfileChanged(index, profile) => {
+                self.is_open = IsOpen::None;
+                if let Some(conn) = self.pulse_state.connection() {
+                    conn.send(pulse::Message::SetCardProfile(index, profile));
+                }
             }
             Message::OutputChanged(val) => {
                 if let Some(conn) = self.pulse_state.connection() {
@@ -475,6 +1368,31 @@ impl cosmic::Application for Audio {
                     }
                 }
             }
+            Message::OutputPortChanged(name, port) => {
+                if let Some(conn) = self.pulse_state.connection() {
+                    conn.send(pulse::Message::SetSinkPortByName(name.clone(), port));
+                    if let Some(val) = self.outputs.iter().find(|o| o.name.as_ref() == Some(&name))
+                    {
+                        if self.current_output.as_ref().map(|o| o.index) != Some(val.index) {
+                            conn.send(pulse::Message::SetDefaultSink(val.clone()));
+                        }
+                    }
+                    conn.send(pulse::Message::GetSinks);
+                    conn.send(pulse::Message::GetDefaultSink);
+                }
+            }
+            Message::InputPortChanged(name, port) => {
+                if let Some(conn) = self.pulse_state.connection() {
+                    conn.send(pulse::Message::SetSourcePortByName(name.clone(), port));
+                    if let Some(val) = self.inputs.iter().find(|i| i.name.as_ref() == Some(&name)) {
+                        if self.current_input.as_ref().map(|i| i.index) != Some(val.index) {
+                            conn.send(pulse::Message::SetDefaultSource(val.clone()));
+                        }
+                    }
+                    conn.send(pulse::Message::GetSources);
+                    conn.send(pulse::Message::GetDefaultSource);
+                }
+            }
             Message::OutputToggle => {
                 self.is_open = if self.is_open == IsOpen::Output {
                     IsOpen::None
@@ -508,6 +1426,9 @@ impl cosmic::Application for Audio {
                         conn.send(pulse::Message::GetSources);
                         conn.send(pulse::Message::GetDefaultSink);
                         conn.send(pulse::Message::GetDefaultSource);
+                        conn.send(pulse::Message::GetSinkInputs);
+                        conn.send(pulse::Message::GetSourceOutputs);
+                        conn.send(pulse::Message::GetCards);
                     }
                 }
                 pulse::Event::MessageReceived(msg) => {
@@ -528,10 +1449,17 @@ impl cosmic::Application for Audio {
                         }
                         pulse::Message::SetDefaultSink(sink) => {
                             self.update_output(Some(sink));
+                            self.emit_dbus_signals();
                         }
                         pulse::Message::SetDefaultSource(source) => {
                             self.update_input(Some(source));
+                            self.emit_dbus_signals();
                         }
+                        pulse::Message::SetSinkInputs(inputs) => self.sink_inputs = inputs,
+                        pulse::Message::SetSourceOutputs(outputs) => self.source_outputs = outputs,
+                        pulse::Message::SetCards(cards) => self.cards = cards,
+                        pulse::Message::SetCombinedSink(active) => self.combined_sink = active,
+                        pulse::Message::SetFeatures(features) => self.features = features,
                         pulse::Message::Disconnected => {
                             panic!("Subscription error handling is bad. This should never happen.")
                         }
@@ -540,43 +1468,87 @@ impl cosmic::Application for Audio {
                         }
                     }
                 }
-                pulse::Event::Disconnected => {
-                    self.pulse_state.disconnected();
-                    if let Some(mut conn) = self.pulse_state.connection().cloned() {
-                        _ = tokio::spawn(async move {
-                            tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-                            conn.send(pulse::Message::UpdateConnection);
-                        });
-                    }
-                }
+                // the pulse thread keeps retrying on its own, and the lists
+                // are requested again once it is connected
+                pulse::Event::Disconnected => self.pulse_state.disconnected(),
             },
+            Message::Reconnect => {
+                if let Some(conn) = self.pulse_state.connection() {
+                    conn.send(pulse::Message::UpdateConnection);
+                }
+            }
             Message::ToggleMediaControlsInTopPanel(chain, enabled) => {
                 self.timeline.set_chain(chain).start();
                 self.config.show_media_controls_in_top_panel = enabled;
-                if let Ok(helper) =
-                    cosmic::cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION)
-                {
-                    if let Err(err) = self.config.write_entry(&helper) {
-                        tracing::error!(?err, "Error writing config");
-                    }
-                }
+                self.write_config();
             }
             Message::CloseRequested(id) => {
                 if Some(id) == self.popup {
                     self.popup = None;
+                    self.save_device_alias();
+                    if let Some(conn) = self.pulse_state.connection() {
+                        conn.send(pulse::Message::Watch(false));
+                    }
                 }
             }
             Message::ConfigChanged(c) => {
                 self.config = c;
+                self.update_player_status();
+                self.clamp_output_volume();
+                self.emit_dbus_signals();
+            }
+            Message::Mpris(mpris_subscription::MprisUpdate::Players(players)) => {
+                self.players = players;
+                self.update_player_status();
+                self.emit_dbus_signals();
             }
-            Message::Mpris(mpris_subscription::MprisUpdate::Player(p)) => {
-                self.player_status = Some(p);
+            Message::Mpris(MprisUpdate::Player(status)) => {
+                // players are sorted by bus name
+                match self
+                    .players
+                    .binary_search_by(|p| p.name().cmp(status.name()))
+                {
+                    Ok(i) => self.players[i] = status,
+                    Err(i) => self.players.insert(i, status),
+                }
+                self.update_player_status();
+                self.emit_dbus_signals();
             }
             Message::Mpris(MprisUpdate::Finished) => {
+                self.players.clear();
                 self.player_status = None;
+                self.emit_dbus_signals();
             }
             Message::Mpris(MprisUpdate::Setup) => {
+                self.players.clear();
                 self.player_status = None;
+                self.emit_dbus_signals();
+            }
+            Message::PlayersToggle => {
+                self.is_open = if self.is_open == IsOpen::Players {
+                    IsOpen::None
+                } else {
+                    IsOpen::Players
+                };
+            }
+            Message::MprisSeek(position) => {
+                self.seek_position = Some(position);
+            }
+            Message::SetPlayerVolume(volume) => {
+                self.player_volume = Some(volume);
+            }
+            // Redraw to move the seek bar along
+            Message::MprisTick => {}
+            Message::PlayerChanged(name) => {
+                self.is_open = IsOpen::None;
+                self.config.preferred_player = self
+                    .players
+                    .iter()
+                    .find(|p| p.name() == name.as_str())
+                    .map(|p| p.id().to_string());
+                self.update_player_status();
+                self.write_config();
+                self.emit_dbus_signals();
             }
             Message::MprisRequest(r) => {
                 let Some(player_status) = self.player_status.as_ref() else {
@@ -584,6 +1556,13 @@ impl cosmic::Application for Audio {
                     return Command::none();
                 };
                 let player = player_status.player.clone();
+                let track_id = player_status.track_id.clone();
+                let current_position = player_status.position();
+                match r {
+                    MprisRequest::SetPosition(_) => self.seek_position = None,
+                    MprisRequest::SetVolume(_) => self.player_volume = None,
+                    _ => {}
+                }
 
                 match r {
                     MprisRequest::Play => tokio::spawn(async move {
@@ -610,6 +1589,40 @@ impl cosmic::Application for Audio {
                             tracing::error!("Error playing previous: {}", err);
                         }
                     }),
+                    MprisRequest::SetPosition(position) => tokio::spawn(async move {
+                        let position = position.as_micros() as i64;
+                        // Without a track id, fall back to a relative seek
+                        let res = match track_id {
+                            Some(track_id) => {
+                                player.inner().set_position(&track_id, position).await
+                            }
+                            None => {
+                                let offset = position - current_position.as_micros() as i64;
+                                player.inner().seek(offset).await
+                            }
+                        };
+                        if let Err(err) = res {
+                            tracing::error!("Error seeking: {}", err);
+                        }
+                    }),
+                    MprisRequest::SetShuffle(shuffle) => tokio::spawn(async move {
+                        let res = player.inner().set_shuffle(shuffle).await;
+                        if let Err(err) = res {
+                            tracing::error!("Error setting shuffle: {}", err);
+                        }
+                    }),
+                    MprisRequest::SetLoopStatus(status) => tokio::spawn(async move {
+                        let res = player.inner().set_loop_status(status.as_str()).await;
+                        if let Err(err) = res {
+                            tracing::error!("Error setting loop status: {}", err);
+                        }
+                    }),
+                    MprisRequest::SetVolume(volume) => tokio::spawn(async move {
+                        let res = player.inner().set_volume(volume).await;
+                        if let Err(err) = res {
+                            tracing::error!("Error setting player volume: {}", err);
+                        }
+                    }),
                 };
             }
             Message::OpenSettings => {
@@ -640,39 +1653,93 @@ impl cosmic::Application for Audio {
                     tokio::spawn(cosmic::process::spawn(cmd));
                 }
             },
-            Message::PulseSub(event) => match event {
-                sub_pulse::Event::SinkVolume(value) => {
-                    self.current_output.as_mut().map(|output| {
-                        output
-                            .volume
-                            .set(output.volume.len(), percent_to_volume(value as f64))
-                    });
-                }
-                sub_pulse::Event::SinkMute(value) => {
-                    if let Some(output) = self.current_output.as_mut() {
-                        output.mute = value;
+            Message::PulseSub(event) => {
+                match event {
+                    sub_pulse::Event::SinkVolume(value) => {
+                        self.current_output
+                            .as_mut()
+                            .map(|output| output.volume.scale(percent_to_volume(value as f64)));
                     }
-                }
-                sub_pulse::Event::SourceVolume(value) => {
-                    self.current_input.as_mut().map(|input| {
-                        input
-                            .volume
-                            .set(input.volume.len(), percent_to_volume(value as f64))
-                    });
-                }
-                sub_pulse::Event::SourceMute(value) => {
-                    if let Some(input) = self.current_input.as_mut() {
-                        input.mute = value;
+                    sub_pulse::Event::SinkMute(value) => {
+                        if let Some(output) = self.current_output.as_mut() {
+                            output.mute = value;
+                        }
+                    }
+                    sub_pulse::Event::SourceVolume(value) => {
+                        self.current_input.as_mut().map(|input| {
+                            input
+                                .volume
+                                .set(input.volume.len(), percent_to_volume(value as f64))
+                        });
+                    }
+                    sub_pulse::Event::SourceMute(value) => {
+                        if let Some(input) = self.current_input.as_mut() {
+                            input.mute = value;
+                        }
                     }
                 }
-            },
+                self.emit_dbus_signals();
+            }
+            Message::Dbus(dbus::Event::Ready(conn)) => {
+                self.dbus = Some(conn);
+                self.dbus_output = None;
+                self.dbus_input = None;
+                self.dbus_media = None;
+                // announce the current state to the new connection
+                self.emit_dbus_signals();
+            }
+            Message::Dbus(dbus::Event::Request(request)) => {
+                let message = match request {
+                    dbus::Request::VolumeUp(step) => Message::SetOutputVolume(
+                        (self.output_volume + f64::from(step)).clamp(0.0, self.max_volume()),
+                    ),
+                    dbus::Request::VolumeDown(step) => Message::SetOutputVolume(
+                        (self.output_volume - f64::from(step)).clamp(0.0, self.max_volume()),
+                    ),
+                    dbus::Request::ToggleOutputMute => {
+                        Message::SetOutputMute(!self.current_output_mute())
+                    }
+                    dbus::Request::ToggleInputMute => {
+                        Message::SetInputMute(!self.current_input_mute())
+                    }
+                    dbus::Request::SetDefaultOutput(name) => Message::OutputChanged(name),
+                    dbus::Request::SetDefaultInput(name) => Message::InputChanged(name),
+                    dbus::Request::PlayPause => match self.is_play() {
+                        Some(true) => Message::MprisRequest(MprisRequest::Play),
+                        Some(false) => Message::MprisRequest(MprisRequest::Pause),
+                        None => return Command::none(),
+                    },
+                    dbus::Request::Next => Message::MprisRequest(MprisRequest::Next),
+                    dbus::Request::Previous => Message::MprisRequest(MprisRequest::Previous),
+                };
+                return self.update(message);
+            }
         };
 
         Command::none()
     }
 
     fn subscription(&self) -> Subscription<Message> {
+        let playing = self
+            .player_status
+            .as_ref()
+            .is_some_and(|s| matches!(s.status, PlaybackStatus::Playing));
+        let mpris_tick = if self.popup.is_some() && playing {
+            iced::time::every(Duration::from_secs(1)).map(|_| Message::MprisTick)
+        } else {
+            Subscription::none()
+        };
+
+        // restarted after a reconnection, as it doesn't recover on its own
+        let pulse_sub = if matches!(self.pulse_state, PulseState::Connected(_)) {
+            sub_pulse::subscription().map(Message::PulseSub)
+        } else {
+            Subscription::none()
+        };
+
         Subscription::batch(vec![
+            mpris_tick,
+            pulse_sub,
             pulse::connect().map(Message::Pulse),
             self.timeline
                 .as_subscription()
@@ -685,7 +1752,7 @@ impl cosmic::Application for Audio {
             }),
             mpris_subscription::mpris_subscription(0).map(Message::Mpris),
             activation_token_subscription(0).map(Message::Token),
-            sub_pulse::subscription().map(Message::PulseSub),
+            dbus::subscription().map(Message::Dbus),
         ])
     }
 
@@ -706,10 +1773,36 @@ impl cosmic::Application for Audio {
             let new_volume = self
                 .current_output
                 .as_ref()
-                .map_or(0f64, |v| volume_to_percent(v.volume.avg()) + change as f64)
-                .clamp(0.0, 100.0);
+                .map_or(0f64, |v| volume_to_percent(v.volume.max()) + change as f64)
+                .clamp(0.0, self.max_volume());
             Message::SetOutputVolume(new_volume)
         });
+        // Privacy indicator, shown while an application is recording
+        let btn: Element<_> = if self.recorded_sources().is_empty() {
+            btn.into()
+        } else {
+            let mic = self
+                .core
+                .applet
+                .icon_button(if self.is_listening() {
+                    "audio-input-microphone-symbolic"
+                } else {
+                    "microphone-sensitivity-muted-symbolic"
+                })
+                .on_press_down(Message::TogglePopup);
+            match self.core.applet.anchor {
+                PanelAnchor::Left | PanelAnchor::Right => {
+                    Column::with_children(vec![btn.into(), mic.into()])
+                        .align_items(Alignment::Center)
+                        .into()
+                }
+                PanelAnchor::Top | PanelAnchor::Bottom => {
+                    Row::with_children(vec![btn.into(), mic.into()])
+                        .align_items(Alignment::Center)
+                        .into()
+                }
+            }
+        };
         let playback_buttons = (!self.core.applet.configure.as_ref().is_some_and(|c| {
             // if we have a configure for width and height, we're in a overflow popup
             c.new_size.0.is_some() && c.new_size.1.is_some()
@@ -740,14 +1833,25 @@ impl cosmic::Application for Audio {
         let in_mute = self.current_input_mute();
 
         let mut audio_content = if audio_disabled {
-            column![padded_control(
-                text(fl!("disconnected"))
+            column![
+                padded_control(
+                    text(fl!("disconnected"))
+                        .width(Length::Fill)
+                        .horizontal_alignment(Horizontal::Center)
+                        .size(24)
+                ),
+                padded_control(
+                    text(fl!("reconnecting"))
+                        .width(Length::Fill)
+                        .horizontal_alignment(Horizontal::Center)
+                ),
+                container(button::text(fl!("retry")).on_press(Message::Reconnect))
                     .width(Length::Fill)
-                    .horizontal_alignment(Horizontal::Center)
-                    .size(24)
-            )]
+                    .center_x()
+                    .padding([0, 0, 8, 0]),
+            ]
         } else {
-            column![
+            let mut content = column![
                 padded_control(
                     row![
                         button::icon(
@@ -759,7 +1863,7 @@ impl cosmic::Application for Audio {
                         .icon_size(24)
                         .line_height(24)
                         .on_press(Message::SetOutputMute(!out_mute)),
-                        slider(0.0..=100.0, self.output_volume, Message::SetOutputVolume)
+                        self.boost_slider(self.output_volume, Message::SetOutputVolume)
                             .width(Length::FillPortion(5)),
                         text(&self.output_volume_text)
                             .size(16)
@@ -795,40 +1899,85 @@ impl cosmic::Application for Audio {
                     self.is_open == IsOpen::Output,
                     fl!("output"),
                     match &self.current_output {
-                        Some(output) => pretty_name(output.description.clone()),
+                        Some(output) => self.device_label(output),
                         None => String::from("No device selected"),
                     },
-                    self.outputs
-                        .clone()
-                        .into_iter()
-                        .map(|output| (
-                            output.name.clone().unwrap_or_default(),
-                            pretty_name(output.description)
-                        ))
-                        .collect(),
+                    self.device_options(&self.outputs),
                     Message::OutputToggle,
-                    Message::OutputChanged,
+                    |(name, port)| match port {
+                        Some(port) => Message::OutputPortChanged(name, port),
+                        None => Message::OutputChanged(name),
+                    },
                 ),
+                self.output_channels(),
+                self.combine_outputs(),
                 revealer(
                     self.is_open == IsOpen::Input,
                     fl!("input"),
                     match &self.current_input {
-                        Some(input) => pretty_name(input.description.clone()),
+                        Some(input) => self.device_label(input),
                         None => fl!("no-device"),
                     },
-                    self.inputs
-                        .clone()
-                        .into_iter()
-                        .map(|input| (
-                            input.name.clone().unwrap_or_default(),
-                            pretty_name(input.description)
-                        ))
-                        .collect(),
+                    self.device_options(&self.inputs),
                     Message::InputToggle,
-                    Message::InputChanged,
+                    |(name, port)| match port {
+                        Some(port) => Message::InputPortChanged(name, port),
+                        None => Message::InputChanged(name),
+                    },
                 )
             ]
-            .align_items(Alignment::Start)
+            .align_items(Alignment::Start);
+
+            // running on a backend other than pulse, say what is missing
+            if self.features != Features::ALL {
+                content = content.push(padded_control(
+                    text::caption(fl!("limited-backend")).width(Length::Fill),
+                ));
+            }
+
+            let cards: Vec<_> = self
+                .cards
+                .iter()
+                .filter(|card| card_profiles(card).len() > 1)
+                .collect();
+            if !cards.is_empty() {
+                content = content
+                    .push(padded_control(divider::horizontal::default()))
+                    .push(padded_control(text::heading(fl!("profiles"))));
+            }
+            for card in cards {
+                let index = card.index;
+                let active = card
+                    .profiles
+                    .iter()
+                    .find(|p| card.active_profile.as_ref() == Some(&p.name))
+                    .map(|p| pretty_name(p.description.clone()))
+                    .unwrap_or_else(|| fl!("no-profile"));
+                content = content.push(revealer(
+                    self.is_open == IsOpen::Card(index),
+                    pretty_name(card.description.clone().or_else(|| card.name.clone())),
+                    active,
+                    card_profiles(card),
+                    Message::CardToggle(index),
+                    move |profile| Message::CardProfileChanged(index, profile),
+                ));
+            }
+
+            if self.visible_streams(StreamKind::Playback).next().is_some() {
+                content = content
+                    .push(padded_control(divider::horizontal::default()))
+                    .push(padded_control(text::heading(fl!("applications"))))
+                    .push(self.streams(StreamKind::Playback));
+            }
+            if self.visible_streams(StreamKind::Recording).next().is_some() {
+                content = content
+                    .push(padded_control(divider::horizontal::default()))
+                    .push(padded_control(text::heading(fl!("recording"))))
+                    .push(self.recorded_sources_view())
+                    .push(self.streams(StreamKind::Recording));
+            }
+
+            content
         };
 
         if let Some(s) = self.player_status.as_ref() {
@@ -907,12 +2056,31 @@ impl cosmic::Application for Audio {
             );
 
             audio_content = audio_content.push(padded_control(divider::horizontal::default()));
+            if self.players.len() > 1 {
+                audio_content = audio_content.push(revealer(
+                    self.is_open == IsOpen::Players,
+                    fl!("media-player"),
+                    player_label(s),
+                    self.players
+                        .iter()
+                        .map(|p| (p.name().to_string(), player_label(p)))
+                        .collect(),
+                    Message::PlayersToggle,
+                    Message::PlayerChanged,
+                ));
+            }
             audio_content = audio_content.push(
                 Row::with_children(elements)
                     .align_items(Alignment::Center)
                     .spacing(8)
                     .padding(menu_control_padding()),
             );
+            if let Some(progress) = self.track_progress(s) {
+                audio_content = audio_content.push(progress);
+            }
+            if let Some(options) = self.player_options(s) {
+                audio_content = audio_content.push(options);
+            }
         }
         let content = column![
             audio_content,
@@ -930,6 +2098,18 @@ impl cosmic::Application for Audio {
                 .width(Length::Fill)
             )
             .padding([8, 24]),
+            self.device_editor(),
+            revealer(
+                self.is_open == IsOpen::MaxVolume,
+                fl!("max-volume"),
+                format!("{}%", self.max_volume()),
+                MAX_VOLUMES
+                    .iter()
+                    .map(|max| (*max, format!("{max}%")))
+                    .collect(),
+                Message::MaxVolumeToggle,
+                Message::MaxVolumeChanged,
+            ),
             padded_control(divider::horizontal::default()),
             menu_button(text::body(fl!("sound-settings"))).on_press(Message::OpenSettings)
         ]
@@ -944,13 +2124,13 @@ impl cosmic::Application for Audio {
     }
 }
 
-fn revealer(
+fn revealer<T: Clone>(
     open: bool,
     title: String,
     selected: String,
-    options: Vec<(String, String)>,
+    options: Vec<(T, String)>,
     toggle: Message,
-    mut change: impl FnMut(String) -> Message + 'static,
+    mut change: impl FnMut(T) -> Message + 'static,
 ) -> widget::Column<'static, Message, crate::Theme, Renderer> {
     if open {
         options.iter().fold(
@@ -982,6 +2162,60 @@ fn revealer_head(
     .on_press(toggle)
 }
 
+fn balance_label(balance: f32) -> String {
+    let percent = (balance.abs() * 100.).round() as u32;
+    if percent == 0 {
+        fl!("balance-center")
+    } else if balance < 0. {
+        fl!("balance-left", percent = percent)
+    } else {
+        fl!("balance-right", percent = percent)
+    }
+}
+
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+fn player_label(player: &mpris_subscription::PlayerStatus) -> String {
+    player
+        .identity
+        .clone()
+        .unwrap_or_else(|| player.name().to_string())
+}
+
+// Profiles that can be selected for a card, by priority. The active profile is
+// kept even if it became unavailable, so it still shows as selected.
+fn card_profiles(card: &CardInfo) -> Vec<(String, String)> {
+    card.profiles
+        .iter()
+        .filter(|p| p.available || card.active_profile.as_ref() == Some(&p.name))
+        .map(|p| (p.name.clone(), pretty_name(p.description.clone())))
+        .collect()
+}
+
+fn stream_icon_name(mute: bool, volume: f64) -> &'static str {
+    if mute || volume == 0. {
+        "audio-volume-muted-symbolic"
+    } else if volume < 33. {
+        "audio-volume-low-symbolic"
+    } else if volume < 66. {
+        "audio-volume-medium-symbolic"
+    } else {
+        "audio-volume-high-symbolic"
+    }
+}
+
 fn pretty_name(name: Option<String>) -> String {
     match name {
         Some(n) => n,
@@ -1032,7 +2266,7 @@ fn volume_to_percent(volume: Volume) -> f64 {
 fn percent_to_volume(percent: f64) -> Volume {
     Volume(
         (percent / 100. * Volume::NORMAL.0 as f64)
-            .clamp(0., Volume::NORMAL.0 as f64)
+            .clamp(0., Volume::MAX.0 as f64)
             .round() as u32,
     )
 }
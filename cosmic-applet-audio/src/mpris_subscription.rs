@@ -1,27 +1,36 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::{borrow::Cow, fmt::Debug, hash::Hash, path::PathBuf};
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    hash::Hash,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use cosmic::{
     iced::{self, subscription},
-    iced_futures::futures::{self, future::OptionFuture, SinkExt, StreamExt},
+    iced_futures::futures::{self, SinkExt, StreamExt},
 };
 use mpris2_zbus::{
     enumerator,
     media_player::MediaPlayer,
+    metadata::Metadata,
     player::{PlaybackStatus, Player},
 };
 use tokio::join;
 use urlencoding::decode;
 use zbus::{
     names::{BusName, OwnedBusName},
+    zvariant::{ObjectPath, OwnedObjectPath},
     Connection,
 };
 
 #[derive(Clone, Debug)]
 pub struct PlayerStatus {
     pub player: Player,
+    pub identity: Option<String>,
     pub icon: Option<PathBuf>,
     pub title: Option<Cow<'static, str>>,
     pub artists: Option<Vec<Cow<'static, str>>>,
@@ -30,11 +39,76 @@ pub struct PlayerStatus {
     pub can_play: bool,
     pub can_go_previous: bool,
     pub can_go_next: bool,
+    pub can_seek: bool,
+    pub can_control: bool,
+    pub track_id: Option<OwnedObjectPath>,
+    pub length: Option<Duration>,
+    /// Position in the track when the status was read.
+    pub position: Duration,
+    pub updated: Instant,
+    pub shuffle: Option<bool>,
+    pub loop_status: Option<LoopStatus>,
+    /// Volume of the player itself, from 0 to 1.
+    pub volume: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoopStatus {
+    None,
+    Track,
+    Playlist,
+}
+
+impl LoopStatus {
+    fn from_str(status: &str) -> Option<Self> {
+        match status {
+            "None" => Some(Self::None),
+            "Track" => Some(Self::Track),
+            "Playlist" => Some(Self::Playlist),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Track => "Track",
+            Self::Playlist => "Playlist",
+        }
+    }
+
+    /// The status a loop button switches to.
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::Playlist,
+            Self::Playlist => Self::Track,
+            Self::Track => Self::None,
+        }
+    }
 }
 
 impl PlayerStatus {
-    async fn new(player: Player) -> Option<Self> {
-        let metadata = player.metadata().await.ok()?;
+    async fn new(mpris_player: &MprisPlayer) -> Option<Self> {
+        let player = mpris_player.player.clone();
+        let proxy = player.inner();
+        // read once, the track id and length from the map and the rest through `Metadata`
+        let track = proxy.metadata().await.ok()?;
+        let track_id = track
+            .get("mpris:trackid")
+            .and_then(|v| v.downcast_ref::<ObjectPath>().ok())
+            .map(OwnedObjectPath::from);
+        // Some players send the length unsigned
+        let length = track
+            .get("mpris:length")
+            .and_then(|v| {
+                v.downcast_ref::<i64>()
+                    .ok()
+                    .or_else(|| v.downcast_ref::<u64>().ok().map(|l| l as i64))
+            })
+            .filter(|l| *l > 0)
+            .map(|l| Duration::from_micros(l as u64));
+
+        let metadata = Metadata::from(track);
         let pathname = metadata.url().unwrap_or("".into());
         let pathbuf = PathBuf::from(pathname);
 
@@ -59,14 +133,25 @@ impl PlayerStatus {
                 }
             });
 
-        let (playback_status, can_pause, can_play, can_go_previous, can_go_next) = join!(
+        let (identity, playback_status, can_pause, can_play, can_go_previous, can_go_next) = join!(
+            mpris_player.media_player.identity(),
             player.playback_status(),
             player.can_pause(),
             player.can_play(),
             player.can_go_previous(),
             player.can_go_next()
         );
+        let (position, shuffle, loop_status, volume, can_seek, can_control) = join!(
+            proxy.position(),
+            proxy.shuffle(),
+            proxy.loop_status(),
+            proxy.volume(),
+            proxy.can_seek(),
+            proxy.can_control()
+        );
+
         Some(Self {
+            identity: identity.ok(),
             icon,
             title,
             artists,
@@ -75,9 +160,48 @@ impl PlayerStatus {
             can_play: can_play.unwrap_or_default(),
             can_go_previous: can_go_previous.unwrap_or_default(),
             can_go_next: can_go_next.unwrap_or_default(),
+            can_seek: can_seek.unwrap_or_default(),
+            can_control: can_control.unwrap_or_default(),
+            track_id,
+            length,
+            position: Duration::from_micros(position.unwrap_or_default().max(0) as u64),
+            updated: Instant::now(),
+            shuffle: shuffle.ok(),
+            loop_status: loop_status.ok().and_then(|s| LoopStatus::from_str(&s)),
+            volume: volume.ok(),
             player,
         })
     }
+
+    /// Bus name of the player.
+    pub fn name(&self) -> &BusName {
+        self.player.inner().destination()
+    }
+
+    /// Bus name without the suffix of the running instance, such as
+    /// `.instance1234` for Firefox and VLC, which is how the player is
+    /// remembered in the config.
+    pub fn id(&self) -> &str {
+        let name = self.name().as_str();
+        match name.rsplit_once('.') {
+            Some((id, instance)) if instance.starts_with("instance") => id,
+            _ => name,
+        }
+    }
+
+    /// Current position in the track, assuming it kept playing since the
+    /// status was read, as players don't signal position changes.
+    pub fn position(&self) -> Duration {
+        let position = if matches!(self.status, PlaybackStatus::Playing) {
+            self.position + self.updated.elapsed()
+        } else {
+            self.position
+        };
+        match self.length {
+            Some(length) => position.min(length),
+            None => position,
+        }
+    }
 }
 
 pub fn mpris_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
@@ -93,16 +217,27 @@ pub fn mpris_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
 #[derive(Clone, Debug)]
 struct MprisPlayer {
     player: Player,
-    #[allow(dead_code)]
     media_player: MediaPlayer,
+    /// Last status read, refreshed when the player signals a change.
+    status: Option<PlayerStatus>,
 }
 
 impl MprisPlayer {
     async fn new(conn: &Connection, name: OwnedBusName) -> mpris2_zbus::error::Result<Self> {
-        Ok(Self {
+        let mut player = Self {
             player: Player::new(conn, name.clone()).await?,
             media_player: MediaPlayer::new(conn, name).await?,
-        })
+            status: None,
+        };
+        player.refresh().await;
+        Ok(player)
+    }
+
+    async fn refresh(&mut self) {
+        self.status = PlayerStatus::new(self).await;
+        if self.status.is_none() {
+            tracing::error!("Failed to get status of {}.", self.name());
+        }
     }
 
     fn name(&self) -> &BusName {
@@ -113,6 +248,9 @@ impl MprisPlayer {
 #[derive(Clone, Debug)]
 pub enum MprisUpdate {
     Setup,
+    /// Every player, sorted by bus name.
+    Players(Vec<PlayerStatus>),
+    /// A player whose status changed.
     Player(PlayerStatus),
     Finished,
 }
@@ -123,6 +261,11 @@ pub enum MprisRequest {
     Pause,
     Next,
     Previous,
+    /// Jump to a position in the current track.
+    SetPosition(Duration),
+    SetShuffle(bool),
+    SetLoopStatus(LoopStatus),
+    SetVolume(f64),
 }
 
 struct State {
@@ -130,9 +273,9 @@ struct State {
     enumerator_stream:
         Box<dyn futures::Stream<Item = zbus::Result<enumerator::Event>> + Unpin + Send>,
     players: Vec<MprisPlayer>,
-    active_player: Option<MprisPlayer>,
-    active_player_metadata_stream: Option<Box<dyn futures::Stream<Item = ()> + Unpin + Send>>,
-    any_player_state_stream: futures::stream::SelectAll<zbus::PropertyStream<'static, String>>,
+    /// Yields the index of a player whenever its status changes.
+    any_player_changed_stream:
+        futures::stream::SelectAll<Box<dyn futures::Stream<Item = usize> + Unpin + Send>>,
 }
 
 fn filter_firefox_players(players: &mut Vec<MprisPlayer>) {
@@ -172,12 +315,9 @@ impl State {
             conn,
             enumerator_stream: Box::new(enumerator_stream),
             players,
-            active_player: None,
-            active_player_metadata_stream: None,
-            any_player_state_stream: futures::stream::select_all(Vec::new()),
+            any_player_changed_stream: futures::stream::select_all(Vec::new()),
         };
-        state.update_active_player().await;
-        state.update_any_player_state_stream().await;
+        state.update_any_player_changed_stream().await;
         Ok(state)
     }
 
@@ -192,45 +332,68 @@ impl State {
         self.players.push(player);
         filter_firefox_players(&mut self.players);
         self.players.sort_by(|a, b| a.name().cmp(&b.name()));
-        self.update_any_player_state_stream().await;
+        self.update_any_player_changed_stream().await;
     }
 
     async fn remove_player(&mut self, name: OwnedBusName) {
         if let Some(idx) = self.players.iter().position(|p| p.name() == &name) {
             self.players.remove(idx);
         }
-        self.update_any_player_state_stream().await;
+        self.update_any_player_changed_stream().await;
     }
 
-    async fn update_active_player(&mut self) {
-        let new_active_player = find_active(&self.players).await;
-        if self.active_player.as_ref().map(|p| p.name()) != new_active_player.map(|p| p.name()) {
-            self.active_player = new_active_player.cloned();
-            if let Some(player) = new_active_player {
-                let controls_changed = futures::stream::select_all([
-                    player.player.receive_can_pause_changed().await,
-                    player.player.receive_can_play_changed().await,
-                    player.player.receive_can_go_previous_changed().await,
-                    player.player.receive_can_go_next_changed().await,
-                ]);
-                let metadata_changed = player.player.receive_metadata_changed().await;
-                let stream = futures::stream::select(
-                    controls_changed.map(|_| ()),
-                    metadata_changed.map(|_| ()),
-                );
-                self.active_player_metadata_stream = Some(Box::new(stream));
-            } else {
-                self.active_player_metadata_stream = None;
+    async fn update_any_player_changed_stream(&mut self) {
+        let mut listeners: Vec<Box<dyn futures::Stream<Item = usize> + Unpin + Send>> =
+            Vec::with_capacity(self.players.len());
+        for (i, p) in self.players.iter().enumerate() {
+            let mut changes: Vec<Box<dyn futures::Stream<Item = ()> + Unpin + Send>> =
+                Vec::with_capacity(6);
+            let player = &p.player;
+            let controls_changed = futures::stream::select_all([
+                player.receive_can_pause_changed().await,
+                player.receive_can_play_changed().await,
+                player.receive_can_go_previous_changed().await,
+                player.receive_can_go_next_changed().await,
+            ]);
+            let metadata_changed = player.receive_metadata_changed().await;
+            let status_changed = player.receive_playback_status_changed().await;
+            changes.push(Box::new(futures::stream::select(
+                controls_changed.map(|_| ()),
+                metadata_changed.map(|_| ()),
+            )));
+            changes.push(Box::new(status_changed.map(|_| ())));
+
+            let proxy = player.inner();
+            let options_changed = futures::stream::select(
+                proxy.receive_shuffle_changed().await.map(|_| ()),
+                proxy.receive_loop_status_changed().await.map(|_| ()),
+            );
+            changes.push(Box::new(options_changed));
+            changes.push(Box::new(proxy.receive_volume_changed().await.map(|_| ())));
+            changes.push(Box::new(proxy.receive_can_seek_changed().await.map(|_| ())));
+            // The position is only reported when the player jumps in the track
+            match proxy.receive_seeked().await {
+                Ok(seeked) => changes.push(Box::new(seeked.map(|_| ()))),
+                Err(err) => tracing::error!("Failed to listen for seeks: {}", err),
             }
+            listeners.push(Box::new(
+                futures::stream::select_all(changes).map(move |()| i),
+            ));
         }
+        self.any_player_changed_stream = futures::stream::select_all(listeners);
     }
 
-    async fn update_any_player_state_stream(&mut self) {
-        let mut listeners = Vec::with_capacity(self.players.len());
-        for p in &self.players {
-            listeners.push(p.player.receive_playback_status_changed().await);
+    async fn send_players(&self, output: &mut futures::channel::mpsc::Sender<MprisUpdate>) {
+        if self.players.is_empty() {
+            let _ = output.send(MprisUpdate::Setup).await;
+        } else {
+            let players = self
+                .players
+                .iter()
+                .filter_map(|p| p.status.clone())
+                .collect();
+            _ = output.send(MprisUpdate::Players(players)).await;
         }
-        self.any_player_state_stream = futures::stream::select_all(listeners);
     }
 }
 
@@ -242,27 +405,10 @@ async fn run(output: &mut futures::channel::mpsc::Sender<MprisUpdate>) {
             return;
         }
     };
+    state.send_players(output).await;
 
     loop {
-        if let Some(player) = &state.active_player {
-            if let Some(player_status) = PlayerStatus::new(player.player.clone()).await {
-                _ = output.send(MprisUpdate::Player(player_status)).await;
-            } else {
-                tracing::error!("Failed to get player status.");
-            };
-        } else {
-            let _ = output.send(MprisUpdate::Setup).await;
-        }
-
-        let metadata_changed_next = OptionFuture::from(
-            state
-                .active_player_metadata_stream
-                .as_mut()
-                .map(|s| s.next()),
-        );
         tokio::select! {
-            _ = metadata_changed_next, if state.active_player.is_some() => {
-            },
             event = state.enumerator_stream.next() => {
                 match event {
                     Some(Ok(enumerator::Event::Add(name))) => state.add_player(name).await,
@@ -273,33 +419,31 @@ async fn run(output: &mut futures::channel::mpsc::Sender<MprisUpdate>) {
                     }
                     None => {}
                 }
-                state.update_active_player().await;
+                state.send_players(output).await;
+            }
+            // only the player that changed is read again
+            Some(i) = state.any_player_changed_stream.next(), if !state.players.is_empty() => {
+                if let Some(player) = state.players.get_mut(i) {
+                    player.refresh().await;
+                    if let Some(status) = player.status.clone() {
+                        _ = output.send(MprisUpdate::Player(status)).await;
+                    }
+                }
             }
-            _ = state.any_player_state_stream.next(), if !state.players.is_empty() => {
-                state.update_active_player().await;
-            },
         };
     }
 }
 
-async fn find_active<'a>(players: &'a Vec<MprisPlayer>) -> Option<&'a MprisPlayer> {
-    let mut best = (0, None::<&'a MprisPlayer>);
-    let eval = |p: Player| async move {
-        let v = {
-            let status = p.playback_status().await;
-
-            match status {
-                Ok(mpris2_zbus::player::PlaybackStatus::Playing) => 100,
-                Ok(mpris2_zbus::player::PlaybackStatus::Paused) => 10,
-                _ => return 0,
-            }
+/// The player to show when none was picked: playing players come before
+/// paused ones, and the first one by bus name wins ties.
+pub fn find_active(players: &[PlayerStatus]) -> Option<&PlayerStatus> {
+    let mut best = (0, None::<&PlayerStatus>);
+    for p in players {
+        let v = match p.status {
+            PlaybackStatus::Playing => 100,
+            PlaybackStatus::Paused => 10,
+            _ => continue,
         };
-
-        v + p.metadata().await.is_ok() as i32
-    };
-
-    for p in players.iter() {
-        let v = eval(p.player.clone()).await;
         if v > best.0 {
             best = (v, Some(p));
         }
@@ -1,7 +1,14 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::{cell::RefCell, mem, rc::Rc, thread};
+use std::{
+    cell::RefCell,
+    mem,
+    rc::Rc,
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
 
 extern crate libpulse_binding as pulse;
 
@@ -13,17 +20,34 @@ use cosmic_time::once_cell::sync::Lazy;
 
 use libpulse_binding::{
     callbacks::ListResult,
+    channelmap::Map as ChannelMap,
     context::{
-        introspect::{Introspector, SinkInfo, SourceInfo},
+        introspect::{
+            CardInfo as PulseCardInfo, Introspector, SinkInfo, SinkInputInfo, SourceInfo,
+            SourceOutputInfo,
+        },
+        subscribe::{Facility, InterestMaskSet},
         Context,
     },
+    def::PortAvailable,
     error::PAErr,
     mainloop::standard::{IterateResult, Mainloop},
     proplist::Proplist,
     volume::ChannelVolumes,
 };
 
-use tokio::sync::{mpsc, Mutex};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::{mpsc, Mutex},
+};
+
+use crate::backend::{self, Backend, BackendType, Change, Features};
+
+/// Name of the sink that plays to several outputs at once.
+pub const COMBINED_SINK_NAME: &str = "cosmic_combined_sink";
+
+// Module of the combined sink loaded by the applet, to unload it on exit
+static COMBINED_SINK_MODULE: AtomicU32 = AtomicU32::new(pulse::def::INVALID_INDEX);
 
 pub static FROM_PULSE: Lazy<Mutex<Option<(mpsc::Receiver<Message>, mpsc::Sender<Message>)>>> =
     Lazy::new(|| Mutex::new(None));
@@ -76,8 +100,7 @@ async fn start_listening(
                 }
                 Some(Message::Disconnected) => {
                     _ = output.send(Event::Disconnected).await;
-
-                    State::Connecting
+                    State::Disconnected
                 }
                 Some(m) => {
                     tracing::error!("Unexpected message: {:?}", m);
@@ -88,56 +111,71 @@ async fn start_listening(
                 }
             }
         }
-        State::Connected => {
+        // Waiting for the pulse thread to reconnect on its own, or after a
+        // retry requested through the connection
+        State::Disconnected => {
             let mut guard = FROM_PULSE.lock().await;
             let Some((from_pulse, _)) = guard.as_mut() else {
                 return State::Connecting;
             };
-            // This is where we match messages from the pulse server to pass to the gui
             match from_pulse.recv().await {
-                Some(Message::SetSinks(sinks)) => {
-                    _ = output
-                        .send(Event::MessageReceived(Message::SetSinks(sinks)))
-                        .await;
-
-                    State::Connected
-                }
-                Some(Message::SetSources(sources)) => {
-                    _ = output
-                        .send(Event::MessageReceived(Message::SetSources(sources)))
-                        .await;
-                    State::Connected
-                }
-                Some(Message::SetDefaultSink(sink)) => {
-                    _ = output
-                        .send(Event::MessageReceived(Message::SetDefaultSink(sink)))
-                        .await;
+                Some(Message::Connected) => {
+                    _ = output.send(Event::Connected).await;
                     State::Connected
                 }
-                Some(Message::SetDefaultSource(source)) => {
-                    _ = output
-                        .send(Event::MessageReceived(Message::SetDefaultSource(source)))
-                        .await;
-                    State::Connected
+                Some(_) => State::Disconnected,
+                None => {
+                    panic!("Pulse Sender dropped, something has gone wrong!");
                 }
+            }
+        }
+        State::Connected => {
+            let mut guard = FROM_PULSE.lock().await;
+            let Some((from_pulse, _)) = guard.as_mut() else {
+                return State::Connecting;
+            };
+            // This is where we match messages from the pulse server to pass to the gui
+            match from_pulse.recv().await {
                 Some(Message::Disconnected) => {
                     _ = output.send(Event::Disconnected).await;
-                    State::Connecting
+                    State::Disconnected
+                }
+                Some(msg) => {
+                    if let Some(event) = received(msg) {
+                        _ = output.send(event).await;
+                    }
+                    State::Connected
                 }
                 None => {
                     _ = output.send(Event::Disconnected).await;
                     State::Connecting
                 }
-                _ => State::Connected,
             }
         }
     }
 }
 
+/// Replies of the pulse thread that are passed on to the applet.
+fn received(msg: Message) -> Option<Event> {
+    match msg {
+        Message::SetSinks(_)
+        | Message::SetSources(_)
+        | Message::SetDefaultSink(_)
+        | Message::SetDefaultSource(_)
+        | Message::SetSinkInputs(_)
+        | Message::SetSourceOutputs(_)
+        | Message::SetCards(_)
+        | Message::SetCombinedSink(_)
+        | Message::SetFeatures(_) => Some(Event::MessageReceived(msg)),
+        _ => None,
+    }
+}
+
 // #[derive(Debug)]
 enum State {
     Connecting,
     Connected,
+    Disconnected,
 }
 
 #[derive(Debug, Clone)]
@@ -186,6 +224,79 @@ pub enum Message {
     SetSourceVolumeByName(String, ChannelVolumes),
     SetSinkMuteByName(String, bool),
     SetSourceMuteByName(String, bool),
+    GetSinkInputs,
+    SetSinkInputs(Vec<StreamInfo>),
+    SetSinkInputVolume(u32, ChannelVolumes),
+    SetSinkInputMute(u32, bool),
+    MoveSinkInput(u32, String),
+    GetSourceOutputs,
+    SetSourceOutputs(Vec<StreamInfo>),
+    SetSourceOutputVolume(u32, ChannelVolumes),
+    SetSourceOutputMute(u32, bool),
+    MoveSourceOutput(u32, String),
+    GetCards,
+    SetCards(Vec<CardInfo>),
+    SetCardProfile(u32, String),
+    SetSinkPortByName(String, String),
+    SetSourcePortByName(String, String),
+    /// Play to the named sinks at once, through a new default sink.
+    LoadCombinedSink(Vec<String>),
+    UnloadCombinedSink,
+    SetCombinedSink(bool),
+    /// Features of the backend, sent after connecting.
+    SetFeatures(Features),
+    /// Whether the popup shows the server state, which is only polled for
+    /// changes often while it does.
+    Watch(bool),
+}
+
+/// Unload the combined sink if the applet loaded one, when the applet exits or
+/// is terminated. A new connection is used, so this works whatever state the
+/// pulse thread is in.
+pub fn unload_combined_sink() {
+    let module = COMBINED_SINK_MODULE.swap(pulse::def::INVALID_INDEX, Ordering::SeqCst);
+    if module == pulse::def::INVALID_INDEX {
+        return;
+    }
+    // only pulse loads combined sinks
+    match BackendType::Pulse.connect() {
+        Ok(mut server) => server.unload_module(module),
+        Err(err) => tracing::error!("Failed to unload combined sink: {:?}", err),
+    }
+}
+
+/// Delay between attempts to reach the sound server, doubling after every
+/// failure up to [`Backoff::MAX`].
+struct Backoff {
+    delay: Duration,
+    next: Option<Instant>,
+}
+
+impl Backoff {
+    const MIN: Duration = Duration::from_secs(1);
+    const MAX: Duration = Duration::from_secs(30);
+
+    fn new() -> Self {
+        Self {
+            delay: Self::MIN,
+            next: None,
+        }
+    }
+
+    // Plan the next attempt after a failed one
+    fn schedule(&mut self) {
+        tracing::info!("Reconnecting to the sound server in {:?}", self.delay);
+        self.next = Some(Instant::now() + self.delay);
+        self.delay = (self.delay * 2).min(Self::MAX);
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn is_due(&self) -> bool {
+        self.next.is_some_and(|next| Instant::now() >= next)
+    }
 }
 
 struct PulseHandle {
@@ -209,219 +320,58 @@ impl PulseHandle {
                 .build()
                 .unwrap();
 
-            // take `PulseServer` and handle reciver into async context
-            // to listen for messages that need to be passed to the pulseserver
+            // take the backend and handle reciver into async context
+            // to listen for messages that need to be passed to the server
             // this lets us put the thread to sleep, but keep hold a single
             // thread, because pulse audio's API is not multithreaded... at all
             rt.block_on(async {
-                let mut server: Option<PulseServer> = None;
+                let mut worker = Worker::new(Box::new(|backend_type| match backend_type {
+                    Some(backend_type) => {
+                        backend_type.connect().map(|server| (backend_type, server))
+                    }
+                    None => backend::connect(),
+                }));
 
                 let mut msgs = Vec::new();
 
+                // pulse only dispatches subscription events while its mainloop
+                // is iterated, so poll the backend periodically between requests
+                let mut period = worker.poll_interval();
+                let mut events = poll_events(period);
+
+                // the panel stops applets with SIGTERM, which would leave the
+                // combined sink behind
+                let mut terminate =
+                    signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+
                 loop {
-                    if let Some(msg) = to_pulse_recv.recv().await {
-                        msgs.push(msg);
+                    let mut replies = Vec::new();
+                    tokio::select! {
+                        msg = to_pulse_recv.recv() => {
+                            if let Some(msg) = msg {
+                                msgs.push(msg);
+                            }
+                        }
+                        _ = events.tick() => replies = worker.poll(&mut msgs),
+                        _ = terminate.recv() => {
+                            unload_combined_sink();
+                            std::process::exit(0);
+                        }
                     }
 
                     // Consume any additional messages in the channel.
                     while let Ok(msg) = to_pulse_recv.try_recv() {
-                        // Deduplicate volume change messages.
-                        if matches!(
-                            msg,
-                            Message::SetSinkVolumeByName(..) | Message::SetSourceVolumeByName(..)
-                        ) {
-                            let last_msg = msgs.last_mut().unwrap(); //
-                            if mem::discriminant(last_msg) == mem::discriminant(&msg) {
-                                *last_msg = msg;
-                                continue;
-                            }
-                        }
-
-                        msgs.push(msg);
+                        queue(&mut msgs, msg);
                     }
 
-                    for msg in msgs.drain(..) {
-                        match msg {
-                            Message::GetDefaultSink => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-                                match server.get_default_sink() {
-                                    Ok(sink) => {
-                                        if let Err(err) = from_pulse_send
-                                            .send(Message::SetDefaultSink(sink))
-                                            .await
-                                        {
-                                            tracing::error!("ERROR! {}", err);
-                                        }
-                                    }
-                                    Err(_) => Self::send_disconnected(&from_pulse_send).await,
-                                }
-                            }
-                            Message::GetDefaultSource => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-                                match server.get_default_source() {
-                                    Ok(source) => {
-                                        if let Err(err) = from_pulse_send
-                                            .send(Message::SetDefaultSource(source))
-                                            .await
-                                        {
-                                            tracing::error!("ERROR! {}", err);
-                                        }
-                                    }
-                                    Err(e) => {
-                                        tracing::error!("ERROR! {:?}", e);
-                                        Self::send_disconnected(&from_pulse_send).await;
-                                    }
-                                }
-                            }
-                            Message::GetSinks => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-                                match server.get_sinks() {
-                                    Ok(sinks) => {
-                                        if let Err(err) =
-                                            from_pulse_send.send(Message::SetSinks(sinks)).await
-                                        {
-                                            tracing::error!("ERROR! {}", err);
-                                        }
-                                    }
-                                    Err(_) => Self::send_disconnected(&from_pulse_send).await,
-                                }
-                            }
-                            Message::GetSources => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-                                match server.get_sources() {
-                                    Ok(sinks) => {
-                                        if let Err(err) =
-                                            from_pulse_send.send(Message::SetSources(sinks)).await
-                                        {
-                                            tracing::error!("ERROR! {}", err);
-                                        }
-                                    }
-                                    Err(_) => Self::send_disconnected(&from_pulse_send).await,
-                                }
-                            }
-                            Message::SetSinkVolumeByName(name, channel_volumes) => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-                                server.set_sink_volume_by_name(&name, &channel_volumes)
-                            }
-                            Message::SetSourceVolumeByName(name, channel_volumes) => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-                                server.set_source_volume_by_name(&name, &channel_volumes)
-                            }
-                            Message::SetSinkMuteByName(name, mute) => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-
-                                let op =
-                                    server.introspector.set_sink_mute_by_name(&name, mute, None);
-                                server.wait_for_result(op).ok();
-                            }
-                            Message::SetSourceMuteByName(name, mute) => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-
-                                let op = server
-                                    .introspector
-                                    .set_source_mute_by_name(&name, mute, None);
-                                server.wait_for_result(op).ok();
-                            }
-                            Message::UpdateConnection => {
-                                tracing::info!(
-                                    "Updating Connection, server exists: {:?}",
-                                    server.is_some()
-                                );
-                                if let Some(mut cur_server) = server.take() {
-                                    if cur_server.get_server_info().is_err() {
-                                        tracing::warn!("got error, server must be disconnected...");
-                                        Self::send_disconnected(&from_pulse_send).await;
-                                    } else {
-                                        tracing::info!("got server info, still connected...");
-                                        server = Some(cur_server);
-                                        Self::send_connected(&from_pulse_send).await;
-                                    }
-                                } else {
-                                    match PulseServer::connect().and_then(|server| server.init()) {
-                                        Ok(new_server) => {
-                                            tracing::info!("Connected to server");
-                                            Self::send_connected(&from_pulse_send).await;
-                                            server = Some(new_server);
-                                        }
-                                        Err(err) => {
-                                            tracing::error!(
-                                                "Failed to connect to server: {:?}",
-                                                err
-                                            );
-                                            Self::send_disconnected(&from_pulse_send).await;
-                                        }
-                                    }
-                                }
-                            }
-                            Message::SetDefaultSink(device) => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-                                let default_sink = match server.get_default_sink() {
-                                    Ok(sink) => sink,
-                                    Err(_) => continue,
-                                };
-                                let to_move = server.get_sink_inputs(default_sink.index);
-                                if let Some(name) = device.name.as_ref() {
-                                    if server.set_default_sink(name, to_move) {
-                                        if let Err(err) = from_pulse_send
-                                            .send(Message::SetDefaultSink(device))
-                                            .await
-                                        {
-                                            tracing::error!("ERROR! {:?}", err);
-                                        };
-                                    }
-                                }
-                            }
-                            Message::SetDefaultSource(device) => {
-                                let server = match server.as_mut() {
-                                    Some(s) => s,
-                                    None => continue,
-                                };
-                                let default_source = match server.get_default_source() {
-                                    Ok(source) => source,
-                                    Err(_) => continue,
-                                };
-                                let to_move = server.get_source_outputs(default_source.index);
-                                if let Some(name) = device.name.as_ref() {
-                                    if server.set_default_source(name, to_move) {
-                                        if let Err(err) = from_pulse_send
-                                            .send(Message::SetDefaultSource(device))
-                                            .await
-                                        {
-                                            tracing::error!("ERROR! {:?}", err);
-                                        };
-                                    }
-                                }
-                            }
-                            _ => {
-                                tracing::warn!("message doesn't match")
-                            }
+                    replies.extend(worker.handle(msgs.drain(..)));
+                    if worker.poll_interval() != period {
+                        period = worker.poll_interval();
+                        events = poll_events(period);
+                    }
+                    for reply in replies {
+                        if let Err(err) = from_pulse_send.send(reply).await {
+                            tracing::error!("ERROR! {}", err);
                         }
                     }
                 }
@@ -432,25 +382,326 @@ impl PulseHandle {
             from_pulse,
         }
     }
+}
+
+// Ticks at which the backend is polled, the first one right away
+fn poll_events(period: Duration) -> tokio::time::Interval {
+    let mut events = tokio::time::interval(period);
+    events.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    events
+}
+
+/// Queue a request, replacing a volume change to the same target that is
+/// still waiting.
+fn queue(msgs: &mut Vec<Message>, msg: Message) {
+    if let Some(last_msg) = msgs.last_mut() {
+        let same_target = match (&*last_msg, &msg) {
+            (Message::SetSinkInputVolume(last, _), Message::SetSinkInputVolume(index, _))
+            | (Message::SetSourceOutputVolume(last, _), Message::SetSourceOutputVolume(index, _)) => {
+                last == index
+            }
+            (last, msg) => {
+                matches!(
+                    msg,
+                    Message::SetSinkVolumeByName(..) | Message::SetSourceVolumeByName(..)
+                ) && mem::discriminant(last) == mem::discriminant(msg)
+            }
+        };
+        if same_target {
+            *last_msg = msg;
+            return;
+        }
+    }
+
+    msgs.push(msg);
+}
+
+type Connector =
+    Box<dyn FnMut(Option<BackendType>) -> backend::Result<(BackendType, Box<dyn Backend>)>>;
+
+/// The sound server as seen from the pulse thread, answering the applet's
+/// requests and keeping the connection up.
+struct Worker {
+    server: Option<Box<dyn Backend>>,
+    // reconnect to the same kind of server, as pipewire-pulse may come back a
+    // moment after PipeWire, and falling back would lose features until the
+    // applet restarts
+    backend_type: Option<BackendType>,
+    // retries connecting once the server is lost, until it is back
+    backoff: Backoff,
+    connect: Connector,
+    // whether the popup is open
+    watching: bool,
+}
 
-    async fn send_disconnected(sender: &tokio::sync::mpsc::Sender<Message>) {
-        sender.send(Message::Disconnected).await.unwrap()
+impl Worker {
+    /// Polling period while the popup shows the server state.
+    const WATCHING: Duration = Duration::from_millis(250);
+    /// Polling period otherwise, as the applet asks for everything again
+    /// when the popup opens.
+    const IDLE: Duration = Duration::from_secs(5);
+
+    fn new(connect: Connector) -> Self {
+        Self {
+            server: None,
+            backend_type: None,
+            backoff: Backoff::new(),
+            connect,
+            watching: false,
+        }
     }
 
-    #[allow(dead_code)]
-    async fn send_connected(sender: &tokio::sync::mpsc::Sender<Message>) {
-        sender.send(Message::Connected).await.unwrap()
+    /// How often to call [`Worker::poll`].
+    fn poll_interval(&self) -> Duration {
+        match self.server {
+            // only to retry connecting once it is due
+            None => Backoff::MIN,
+            Some(_) if self.watching => Self::WATCHING,
+            Some(_) => Self::IDLE,
+        }
+    }
+
+    /// Queue the requests for whatever changed on the server, or a retry
+    /// once it is due while disconnected.
+    fn poll(&mut self, msgs: &mut Vec<Message>) -> Vec<Message> {
+        let Some(server) = self.server.as_mut() else {
+            if self.backoff.is_due() && !msgs.contains(&Message::UpdateConnection) {
+                msgs.push(Message::UpdateConnection);
+            }
+            return Vec::new();
+        };
+        let changes = match server.poll_events() {
+            Ok(changes) => changes,
+            Err(err) => {
+                tracing::warn!("Lost connection to server: {:?}", err);
+                return self.lost();
+            }
+        };
+        for change in changes {
+            let requests = match change {
+                Change::Sinks => vec![Message::GetSinks, Message::GetDefaultSink],
+                Change::Sources => vec![Message::GetSources, Message::GetDefaultSource],
+                Change::SinkInputs => vec![Message::GetSinkInputs],
+                Change::SourceOutputs => vec![Message::GetSourceOutputs],
+                // profile changes replace the card's sinks and sources
+                Change::Cards => vec![
+                    Message::GetCards,
+                    Message::GetSinks,
+                    Message::GetSources,
+                    Message::GetDefaultSink,
+                    Message::GetDefaultSource,
+                ],
+                Change::Server => vec![Message::GetDefaultSink, Message::GetDefaultSource],
+            };
+            for msg in requests {
+                if !msgs.contains(&msg) {
+                    msgs.push(msg);
+                }
+            }
+        }
+        Vec::new()
+    }
+
+    // The server went away, drop it until the next attempt
+    fn lost(&mut self) -> Vec<Message> {
+        self.server = None;
+        self.backoff.schedule();
+        vec![Message::Disconnected]
+    }
+
+    fn update_connection(&mut self) -> Vec<Message> {
+        tracing::info!(
+            "Updating Connection, server exists: {:?}",
+            self.server.is_some()
+        );
+        if let Some(server) = self.server.as_mut() {
+            if server.is_connected() {
+                tracing::info!("got server info, still connected...");
+                return vec![Message::Connected];
+            }
+            tracing::warn!("got error, server must be disconnected...");
+            return self.lost();
+        }
+
+        match (self.connect)(self.backend_type) {
+            Ok((backend_type, server)) => {
+                tracing::info!("Connected to server");
+                self.backend_type = Some(backend_type);
+                self.backoff.reset();
+                let features = server.features();
+                self.server = Some(server);
+                vec![Message::Connected, Message::SetFeatures(features)]
+            }
+            Err(err) => {
+                tracing::error!("Failed to connect to server: {:?}", err);
+                self.backoff.schedule();
+                vec![Message::Disconnected]
+            }
+        }
+    }
+
+    /// Carry out the queued requests, and return the replies for the applet.
+    fn handle(&mut self, msgs: impl IntoIterator<Item = Message>) -> Vec<Message> {
+        let mut replies = Vec::new();
+        for msg in msgs {
+            match msg {
+                Message::UpdateConnection => {
+                    replies.extend(self.update_connection());
+                    continue;
+                }
+                Message::Watch(watching) => {
+                    self.watching = watching;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let server = match self.server.as_mut() {
+                Some(s) => s,
+                None => continue,
+            };
+
+            let reply = match msg {
+                Message::GetDefaultSink => server.get_default_sink().map(Message::SetDefaultSink),
+                Message::GetDefaultSource => {
+                    server.get_default_source().map(Message::SetDefaultSource)
+                }
+                Message::GetSinks => server.get_sinks().map(Message::SetSinks),
+                Message::GetSources => server.get_sources().map(Message::SetSources),
+                Message::GetSinkInputs => server.get_sink_input_list().map(Message::SetSinkInputs),
+                Message::GetSourceOutputs => server
+                    .get_source_output_list()
+                    .map(Message::SetSourceOutputs),
+                Message::GetCards => server.get_cards().map(Message::SetCards),
+                Message::SetSinkVolumeByName(name, channel_volumes) => {
+                    server.set_sink_volume_by_name(&name, &channel_volumes);
+                    continue;
+                }
+                Message::SetSourceVolumeByName(name, channel_volumes) => {
+                    server.set_source_volume_by_name(&name, &channel_volumes);
+                    continue;
+                }
+                Message::SetSinkMuteByName(name, mute) => {
+                    server.set_sink_mute_by_name(&name, mute);
+                    continue;
+                }
+                Message::SetSourceMuteByName(name, mute) => {
+                    server.set_source_mute_by_name(&name, mute);
+                    continue;
+                }
+                Message::SetSinkInputVolume(index, channel_volumes) => {
+                    server.set_sink_input_volume(index, &channel_volumes);
+                    continue;
+                }
+                Message::SetSinkInputMute(index, mute) => {
+                    server.set_sink_input_mute(index, mute);
+                    continue;
+                }
+                Message::MoveSinkInput(index, name) => {
+                    server.move_sink_input(index, &name);
+                    continue;
+                }
+                Message::SetSourceOutputVolume(index, channel_volumes) => {
+                    server.set_source_output_volume(index, &channel_volumes);
+                    continue;
+                }
+                Message::SetSourceOutputMute(index, mute) => {
+                    server.set_source_output_mute(index, mute);
+                    continue;
+                }
+                Message::MoveSourceOutput(index, name) => {
+                    server.move_source_output(index, &name);
+                    continue;
+                }
+                Message::SetCardProfile(index, profile) => {
+                    server.set_card_profile(index, &profile);
+                    continue;
+                }
+                Message::SetSinkPortByName(name, port) => {
+                    server.set_sink_port_by_name(&name, &port);
+                    continue;
+                }
+                Message::SetSourcePortByName(name, port) => {
+                    server.set_source_port_by_name(&name, &port);
+                    continue;
+                }
+                Message::LoadCombinedSink(sinks) => {
+                    let module =
+                        COMBINED_SINK_MODULE.swap(pulse::def::INVALID_INDEX, Ordering::SeqCst);
+                    if module != pulse::def::INVALID_INDEX {
+                        server.unload_module(module);
+                    }
+                    match server.load_combined_sink(&sinks) {
+                        Ok(module) => {
+                            COMBINED_SINK_MODULE.store(module, Ordering::SeqCst);
+                            server.set_default_sink(COMBINED_SINK_NAME);
+                            Ok(Message::SetCombinedSink(true))
+                        }
+                        Err(err) => {
+                            tracing::error!("Failed to load combined sink: {:?}", err);
+                            continue;
+                        }
+                    }
+                }
+                Message::UnloadCombinedSink => {
+                    let module =
+                        COMBINED_SINK_MODULE.swap(pulse::def::INVALID_INDEX, Ordering::SeqCst);
+                    if module != pulse::def::INVALID_INDEX {
+                        server.unload_module(module);
+                    }
+                    Ok(Message::SetCombinedSink(false))
+                }
+                Message::SetDefaultSink(device) => {
+                    let Some(name) = device.name.as_ref() else {
+                        continue;
+                    };
+                    if !server.set_default_sink(name) {
+                        continue;
+                    }
+                    Ok(Message::SetDefaultSink(device))
+                }
+                Message::SetDefaultSource(device) => {
+                    let Some(name) = device.name.as_ref() else {
+                        continue;
+                    };
+                    if !server.set_default_source(name) {
+                        continue;
+                    }
+                    Ok(Message::SetDefaultSource(device))
+                }
+                _ => {
+                    tracing::warn!("message doesn't match");
+                    continue;
+                }
+            };
+
+            match reply {
+                Ok(reply) => replies.push(reply),
+                Err(err @ (backend::Error::Connect(_) | backend::Error::Disconnected)) => {
+                    // requests still queued are dropped, the applet asks for
+                    // everything again once reconnected
+                    tracing::error!("Lost connection to server: {:?}", err);
+                    replies.extend(self.lost());
+                    break;
+                }
+                Err(err) => {
+                    tracing::error!("ERROR! {:?}", err);
+                }
+            }
+        }
+        replies
     }
 }
 
-struct PulseServer {
+pub(crate) struct PulseServer {
     mainloop: Rc<RefCell<Mainloop>>,
     context: Rc<RefCell<Context>>,
     introspector: Introspector,
+    events: Rc<RefCell<Vec<Facility>>>,
 }
 
 #[derive(Clone, Debug)]
-enum PulseServerError<'a> {
+pub(crate) enum PulseServerError<'a> {
     IterateErr(IterateResult),
     ContextErr(pulse::context::State),
     OperationErr(pulse::operation::State),
@@ -493,6 +744,7 @@ impl PulseServer {
             mainloop,
             context,
             introspector,
+            events: Rc::new(RefCell::new(Vec::new())),
         })
     }
 
@@ -525,6 +777,139 @@ impl PulseServer {
         Ok(self)
     }
 
+    // Record change events from the server, to be collected by dispatch_events()
+    pub fn subscribe(&mut self) -> Result<(), PulseServerError> {
+        let events = self.events.clone();
+        self.context
+            .borrow_mut()
+            .set_subscribe_callback(Some(Box::new(move |facility, _, _| {
+                if let Some(facility) = facility {
+                    let mut events = events.borrow_mut();
+                    if !events.contains(&facility) {
+                        events.push(facility);
+                    }
+                }
+            })));
+
+        let op = self.context.borrow_mut().subscribe(
+            InterestMaskSet::SINK
+                | InterestMaskSet::SOURCE
+                | InterestMaskSet::SINK_INPUT
+                | InterestMaskSet::SOURCE_OUTPUT
+                | InterestMaskSet::CARD
+                | InterestMaskSet::SERVER,
+            |_| {},
+        );
+        self.wait_for_result(op)
+    }
+
+    // Dispatch pending server events without blocking and return the
+    // facilities that changed since the last call
+    pub fn dispatch_events(&mut self) -> Result<Vec<Facility>, PulseServerError> {
+        loop {
+            match self.mainloop.borrow_mut().iterate(false) {
+                IterateResult::Success(n) if n > 0 => {}
+                IterateResult::Success(_) => break,
+                err => return Err(PulseServerError::IterateErr(err)),
+            }
+        }
+        match self.context.borrow().get_state() {
+            state @ (pulse::context::State::Failed | pulse::context::State::Terminated) => {
+                return Err(PulseServerError::ContextErr(state))
+            }
+            _ => {}
+        }
+        Ok(mem::take(&mut *self.events.borrow_mut()))
+    }
+
+    // Get a list of playback streams
+    pub fn get_sink_input_list(&self) -> Result<Vec<StreamInfo>, PulseServerError> {
+        let list: Rc<RefCell<Option<Vec<StreamInfo>>>> = Rc::new(RefCell::new(Some(Vec::new())));
+        let list_ref = list.clone();
+
+        let operation = self.introspector.get_sink_input_info_list(
+            move |input_list: ListResult<&SinkInputInfo>| {
+                if let ListResult::Item(item) = input_list {
+                    list_ref.borrow_mut().as_mut().unwrap().push(item.into());
+                }
+            },
+        );
+        self.wait_for_result(operation).and_then(|_| {
+            list.borrow_mut().take().ok_or(PulseServerError::Misc(
+                "get_sink_input_list(): failed to wait for operation",
+            ))
+        })
+    }
+
+    // Get a list of recording streams
+    pub fn get_source_output_list(&self) -> Result<Vec<StreamInfo>, PulseServerError> {
+        let list: Rc<RefCell<Option<Vec<StreamInfo>>>> = Rc::new(RefCell::new(Some(Vec::new())));
+        let list_ref = list.clone();
+
+        let operation = self.introspector.get_source_output_info_list(
+            move |output_list: ListResult<&SourceOutputInfo>| {
+                if let ListResult::Item(item) = output_list {
+                    list_ref.borrow_mut().as_mut().unwrap().push(item.into());
+                }
+            },
+        );
+        self.wait_for_result(operation).and_then(|_| {
+            list.borrow_mut().take().ok_or(PulseServerError::Misc(
+                "get_source_output_list(): failed to wait for operation",
+            ))
+        })
+    }
+
+    // Load module-combine-sink over the given sinks
+    pub fn load_combined_sink(&mut self, sinks: &[String]) -> Result<u32, PulseServerError> {
+        let index = Rc::new(RefCell::new(None));
+        let index_ref = index.clone();
+        let argument = format!(
+            "sink_name={} slaves={}",
+            COMBINED_SINK_NAME,
+            sinks.join(",")
+        );
+
+        let operation =
+            self.introspector
+                .load_module("module-combine-sink", &argument, move |module| {
+                    *index_ref.borrow_mut() = Some(module);
+                });
+        self.wait_for_result(operation).and_then(|_| {
+            index
+                .borrow_mut()
+                .take()
+                .filter(|module| *module != pulse::def::INVALID_INDEX)
+                .ok_or(PulseServerError::Misc(
+                    "load_combined_sink(): failed to load module",
+                ))
+        })
+    }
+
+    pub fn unload_module(&mut self, index: u32) -> Result<(), PulseServerError> {
+        let operation = self.introspector.unload_module(index, |_| {});
+        self.wait_for_result(operation)
+    }
+
+    // Get a list of sound cards and their profiles
+    pub fn get_cards(&self) -> Result<Vec<CardInfo>, PulseServerError> {
+        let list: Rc<RefCell<Option<Vec<CardInfo>>>> = Rc::new(RefCell::new(Some(Vec::new())));
+        let list_ref = list.clone();
+
+        let operation =
+            self.introspector
+                .get_card_info_list(move |card_list: ListResult<&PulseCardInfo>| {
+                    if let ListResult::Item(item) = card_list {
+                        list_ref.borrow_mut().as_mut().unwrap().push(item.into());
+                    }
+                });
+        self.wait_for_result(operation).and_then(|_| {
+            list.borrow_mut().take().ok_or(PulseServerError::Misc(
+                "get_cards(): failed to wait for operation",
+            ))
+        })
+    }
+
     // Get a list of output devices
     pub fn get_sinks(&self) -> Result<Vec<DeviceInfo>, PulseServerError> {
         let list: Rc<RefCell<Option<Vec<DeviceInfo>>>> = Rc::new(RefCell::new(Some(Vec::new())));
@@ -687,33 +1072,9 @@ impl PulseServer {
         }
     }
 
-    fn set_sink_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
-        let op = self
-            .introspector
-            .set_sink_mute_by_name(name, volume.is_muted(), None);
-        self.wait_for_result(op).ok();
-
-        let op = self
-            .introspector
-            .set_sink_volume_by_name(name, volume, None);
-        self.wait_for_result(op).ok();
-    }
-
-    fn set_source_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
-        let op = self
-            .introspector
-            .set_source_mute_by_name(name, volume.is_muted(), None);
-        let _ = self.wait_for_result(op);
-
-        let op = self
-            .introspector
-            .set_source_volume_by_name(name, volume, None);
-        let _ = self.wait_for_result(op);
-    }
-
     fn get_source_outputs(&mut self, source: u32) -> Vec<u32> {
         let result = Rc::new(RefCell::new(Vec::new()));
-        let result_ref = Rc::new(RefCell::new(Vec::new()));
+        let result_ref = result.clone();
         let op = self.introspector.get_source_output_info_list(move |list| {
             if let ListResult::Item(item) = list {
                 if source == item.source {
@@ -727,7 +1088,7 @@ impl PulseServer {
 
     fn get_sink_inputs(&mut self, sink: u32) -> Vec<u32> {
         let result = Rc::new(RefCell::new(Vec::new()));
-        let result_ref = Rc::new(RefCell::new(Vec::new()));
+        let result_ref = result.clone();
         let op = self.introspector.get_sink_input_info_list(move |list| {
             if let ListResult::Item(item) = list {
                 if sink == item.sink {
@@ -771,13 +1132,190 @@ impl PulseServer {
     }
 }
 
+impl From<PulseServerError<'_>> for backend::Error {
+    fn from(err: PulseServerError<'_>) -> Self {
+        match err {
+            PulseServerError::IterateErr(_) | PulseServerError::ContextErr(_) => Self::Disconnected,
+            err => Self::Misc(format!("{:?}", err)),
+        }
+    }
+}
+
+impl Backend for PulseServer {
+    fn features(&self) -> Features {
+        Features::ALL
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.get_server_info().is_ok()
+    }
+
+    fn poll_events(&mut self) -> backend::Result<Vec<Change>> {
+        Ok(self
+            .dispatch_events()?
+            .into_iter()
+            .filter_map(|facility| match facility {
+                Facility::Sink => Some(Change::Sinks),
+                Facility::Source => Some(Change::Sources),
+                Facility::SinkInput => Some(Change::SinkInputs),
+                Facility::SourceOutput => Some(Change::SourceOutputs),
+                Facility::Card => Some(Change::Cards),
+                Facility::Server => Some(Change::Server),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn get_sinks(&mut self) -> backend::Result<Vec<DeviceInfo>> {
+        Ok(PulseServer::get_sinks(self)?)
+    }
+
+    fn get_sources(&mut self) -> backend::Result<Vec<DeviceInfo>> {
+        Ok(PulseServer::get_sources(self)?)
+    }
+
+    fn get_default_sink(&mut self) -> backend::Result<DeviceInfo> {
+        Ok(PulseServer::get_default_sink(self)?)
+    }
+
+    fn get_default_source(&mut self) -> backend::Result<DeviceInfo> {
+        Ok(PulseServer::get_default_source(self)?)
+    }
+
+    fn set_default_sink(&mut self, name: &str) -> bool {
+        let Ok(default_sink) = PulseServer::get_default_sink(self) else {
+            return false;
+        };
+        let to_move = self.get_sink_inputs(default_sink.index);
+        PulseServer::set_default_sink(self, name, to_move)
+    }
+
+    fn set_default_source(&mut self, name: &str) -> bool {
+        let Ok(default_source) = PulseServer::get_default_source(self) else {
+            return false;
+        };
+        let to_move = self.get_source_outputs(default_source.index);
+        PulseServer::set_default_source(self, name, to_move)
+    }
+
+    fn set_sink_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
+        let op = self
+            .introspector
+            .set_sink_mute_by_name(name, volume.is_muted(), None);
+        self.wait_for_result(op).ok();
+
+        let op = self
+            .introspector
+            .set_sink_volume_by_name(name, volume, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn set_source_volume_by_name(&mut self, name: &str, volume: &ChannelVolumes) {
+        let op = self
+            .introspector
+            .set_source_mute_by_name(name, volume.is_muted(), None);
+        let _ = self.wait_for_result(op);
+
+        let op = self
+            .introspector
+            .set_source_volume_by_name(name, volume, None);
+        let _ = self.wait_for_result(op);
+    }
+
+    fn set_sink_mute_by_name(&mut self, name: &str, mute: bool) {
+        let op = self.introspector.set_sink_mute_by_name(name, mute, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn set_source_mute_by_name(&mut self, name: &str, mute: bool) {
+        let op = self.introspector.set_source_mute_by_name(name, mute, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn get_sink_input_list(&mut self) -> backend::Result<Vec<StreamInfo>> {
+        Ok(PulseServer::get_sink_input_list(self)?)
+    }
+
+    fn set_sink_input_volume(&mut self, index: u32, volume: &ChannelVolumes) {
+        let op = self.introspector.set_sink_input_volume(index, volume, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn set_sink_input_mute(&mut self, index: u32, mute: bool) {
+        let op = self.introspector.set_sink_input_mute(index, mute, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn move_sink_input(&mut self, index: u32, sink: &str) {
+        let op = self.introspector.move_sink_input_by_name(index, sink, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn get_source_output_list(&mut self) -> backend::Result<Vec<StreamInfo>> {
+        Ok(PulseServer::get_source_output_list(self)?)
+    }
+
+    fn set_source_output_volume(&mut self, index: u32, volume: &ChannelVolumes) {
+        let op = self
+            .introspector
+            .set_source_output_volume(index, volume, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn set_source_output_mute(&mut self, index: u32, mute: bool) {
+        let op = self.introspector.set_source_output_mute(index, mute, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn move_source_output(&mut self, index: u32, source: &str) {
+        let op = self
+            .introspector
+            .move_source_output_by_name(index, source, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn get_cards(&mut self) -> backend::Result<Vec<CardInfo>> {
+        Ok(PulseServer::get_cards(self)?)
+    }
+
+    fn set_card_profile(&mut self, index: u32, profile: &str) {
+        let op = self
+            .introspector
+            .set_card_profile_by_index(index, profile, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn set_sink_port_by_name(&mut self, name: &str, port: &str) {
+        let op = self.introspector.set_sink_port_by_name(name, port, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn set_source_port_by_name(&mut self, name: &str, port: &str) {
+        let op = self.introspector.set_source_port_by_name(name, port, None);
+        self.wait_for_result(op).ok();
+    }
+
+    fn load_combined_sink(&mut self, sinks: &[String]) -> backend::Result<u32> {
+        Ok(PulseServer::load_combined_sink(self, sinks)?)
+    }
+
+    fn unload_module(&mut self, index: u32) {
+        if let Err(err) = PulseServer::unload_module(self, index) {
+            tracing::error!("Failed to unload module {}: {:?}", index, err);
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct DeviceInfo {
     pub name: Option<String>,
     pub description: Option<String>,
     pub volume: ChannelVolumes,
+    pub channel_map: ChannelMap,
     pub mute: bool,
     pub index: u32,
+    pub ports: Vec<PortInfo>,
+    pub active_port: Option<String>,
 }
 
 impl<'a> From<&SinkInfo<'a>> for DeviceInfo {
@@ -786,8 +1324,26 @@ impl<'a> From<&SinkInfo<'a>> for DeviceInfo {
             name: info.name.clone().map(|x| x.into_owned()),
             description: info.description.clone().map(|x| x.into_owned()),
             volume: info.volume,
+            channel_map: info.channel_map,
             mute: info.mute,
             index: info.index,
+            ports: info
+                .ports
+                .iter()
+                .filter_map(|port| {
+                    Some(PortInfo {
+                        name: port.name.clone()?.into_owned(),
+                        description: port.description.clone().map(|x| x.into_owned()),
+                        priority: port.priority,
+                        available: port.available,
+                    })
+                })
+                .collect(),
+            active_port: info
+                .active_port
+                .as_ref()
+                .and_then(|p| p.name.clone())
+                .map(|x| x.into_owned()),
         }
     }
 }
@@ -798,14 +1354,144 @@ impl<'a> From<&SourceInfo<'a>> for DeviceInfo {
             name: info.name.clone().map(|x| x.into_owned()),
             description: info.description.clone().map(|x| x.into_owned()),
             volume: info.volume,
+            channel_map: info.channel_map,
             mute: info.mute,
             index: info.index,
+            ports: info
+                .ports
+                .iter()
+                .filter_map(|port| {
+                    Some(PortInfo {
+                        name: port.name.clone()?.into_owned(),
+                        description: port.description.clone().map(|x| x.into_owned()),
+                        priority: port.priority,
+                        available: port.available,
+                    })
+                })
+                .collect(),
+            active_port: info
+                .active_port
+                .as_ref()
+                .and_then(|p| p.name.clone())
+                .map(|x| x.into_owned()),
         }
     }
 }
 
+/// A jack or connector of a sink or source, such as speakers or headphones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortInfo {
+    pub name: String,
+    pub description: Option<String>,
+    pub priority: u32,
+    /// Whether something is plugged into the port, if the card can detect it.
+    pub available: PortAvailable,
+}
+
 impl Eq for DeviceInfo {}
 
+/// An application stream, playing to a sink or recording from a source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub index: u32,
+    /// Index of the sink or source the stream is connected to.
+    pub device: u32,
+    pub application_name: Option<String>,
+    pub media_name: Option<String>,
+    pub icon_name: Option<String>,
+    pub volume: ChannelVolumes,
+    pub mute: bool,
+}
+
+impl<'a> From<&SinkInputInfo<'a>> for StreamInfo {
+    fn from(info: &SinkInputInfo<'a>) -> Self {
+        Self {
+            index: info.index,
+            device: info.sink,
+            application_name: info
+                .proplist
+                .get_str(pulse::proplist::properties::APPLICATION_NAME),
+            media_name: info.name.clone().map(|x| x.into_owned()),
+            icon_name: info
+                .proplist
+                .get_str(pulse::proplist::properties::APPLICATION_ICON_NAME),
+            volume: info.volume,
+            mute: info.mute,
+        }
+    }
+}
+
+impl<'a> From<&SourceOutputInfo<'a>> for StreamInfo {
+    fn from(info: &SourceOutputInfo<'a>) -> Self {
+        Self {
+            index: info.index,
+            device: info.source,
+            application_name: info
+                .proplist
+                .get_str(pulse::proplist::properties::APPLICATION_NAME),
+            media_name: info.name.clone().map(|x| x.into_owned()),
+            icon_name: info
+                .proplist
+                .get_str(pulse::proplist::properties::APPLICATION_ICON_NAME),
+            volume: info.volume,
+            mute: info.mute,
+        }
+    }
+}
+
+impl Eq for StreamInfo {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardInfo {
+    pub index: u32,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Profiles ordered from highest to lowest priority.
+    pub profiles: Vec<CardProfile>,
+    pub active_profile: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardProfile {
+    pub name: String,
+    pub description: Option<String>,
+    pub priority: u32,
+    /// Whether the profile can currently be used, e.g. the port it needs is plugged in.
+    pub available: bool,
+}
+
+impl<'a> From<&PulseCardInfo<'a>> for CardInfo {
+    fn from(info: &PulseCardInfo<'a>) -> Self {
+        let mut profiles: Vec<CardProfile> = info
+            .profiles
+            .iter()
+            .filter_map(|profile| {
+                Some(CardProfile {
+                    name: profile.name.clone()?.into_owned(),
+                    description: profile.description.clone().map(|x| x.into_owned()),
+                    priority: profile.priority,
+                    available: profile.available,
+                })
+            })
+            .collect();
+        profiles.sort_by(|a, b| b.priority.cmp(&a.priority));
+
+        Self {
+            index: info.index,
+            name: info.name.clone().map(|x| x.into_owned()),
+            description: info
+                .proplist
+                .get_str(pulse::proplist::properties::DEVICE_DESCRIPTION),
+            profiles,
+            active_profile: info
+                .active_profile
+                .as_ref()
+                .and_then(|p| p.name.clone())
+                .map(|x| x.into_owned()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ServerInfo {
     /// User name of the daemon process.
@@ -843,3 +1529,220 @@ impl<'a> From<&'a pulse::context::introspect::ServerInfo<'a>> for ServerInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use cosmic::Application;
+
+    use super::*;
+    use crate::{backend::mock::MockBackend, Audio, PulseState, StreamKind};
+
+    /// The applet and the pulse thread, connected to a mock server.
+    struct Session {
+        audio: Audio,
+        worker: Worker,
+        to_pulse: mpsc::Receiver<Message>,
+        running: Rc<Cell<bool>>,
+    }
+
+    impl Session {
+        fn new() -> Self {
+            let running = Rc::new(Cell::new(true));
+            let server = running.clone();
+            let worker = Worker::new(Box::new(move |_| {
+                if !server.get() {
+                    return Err(backend::Error::Connect("server is down".to_string()));
+                }
+                // a restarted server starts over
+                let backend: Box<dyn Backend> = Box::new(MockBackend::with_server(server.clone()));
+                Ok((BackendType::Mock, backend))
+            }));
+            let (tx, to_pulse) = mpsc::channel(50);
+            let mut session = Self {
+                audio: Audio::default(),
+                worker,
+                to_pulse,
+                running,
+            };
+            session.update(crate::Message::Pulse(Event::Init(Connection(tx))));
+            session.run();
+            session
+        }
+
+        fn update(&mut self, message: crate::Message) {
+            let _ = self.audio.update(message);
+        }
+
+        // Pass requests of the applet to the pulse thread and its replies
+        // back, the way the subscription does, until nothing is left
+        fn run(&mut self) {
+            loop {
+                let mut msgs = Vec::new();
+                while let Ok(msg) = self.to_pulse.try_recv() {
+                    queue(&mut msgs, msg);
+                }
+                let mut replies = self.worker.poll(&mut msgs);
+                if msgs.is_empty() && replies.is_empty() {
+                    return;
+                }
+                replies.extend(self.worker.handle(msgs));
+                for reply in replies {
+                    let event = match reply {
+                        Message::Connected => Some(Event::Connected),
+                        Message::Disconnected => Some(Event::Disconnected),
+                        reply => received(reply),
+                    };
+                    if let Some(event) = event {
+                        self.update(crate::Message::Pulse(event));
+                    }
+                }
+            }
+        }
+
+        fn default_output(&self) -> Option<&str> {
+            self.audio.current_output.as_ref()?.name.as_deref()
+        }
+    }
+
+    #[test]
+    fn connects_and_lists_everything() {
+        let session = Session::new();
+        let audio = &session.audio;
+        assert!(matches!(audio.pulse_state, PulseState::Connected(_)));
+        assert_eq!(audio.features, MockBackend::default().features());
+        assert_eq!(audio.outputs.len(), 2);
+        assert_eq!(audio.inputs.len(), 1);
+        assert_eq!(session.default_output(), Some("mock_output.speakers"));
+        assert_eq!(
+            audio.current_input.as_ref().unwrap().name.as_deref(),
+            Some("mock_input.microphone")
+        );
+        assert_eq!(audio.sink_inputs.len(), 1);
+        assert_eq!(audio.source_outputs.len(), 1);
+    }
+
+    #[test]
+    fn default_output_moves_streams() {
+        let mut session = Session::new();
+        session.update(crate::Message::OutputChanged(
+            "mock_output.headphones".to_string(),
+        ));
+        session.run();
+
+        assert_eq!(session.default_output(), Some("mock_output.headphones"));
+        assert!(session.audio.sink_inputs.iter().all(|s| s.device == 1));
+        // streams of the unchanged input stay where they are
+        assert!(session.audio.source_outputs.iter().all(|s| s.device == 0));
+    }
+
+    #[test]
+    fn unknown_output_is_ignored() {
+        let mut session = Session::new();
+        session.update(crate::Message::OutputChanged("missing".to_string()));
+        session.run();
+
+        assert_eq!(session.default_output(), Some("mock_output.speakers"));
+        assert!(session.audio.sink_inputs.iter().all(|s| s.device == 0));
+    }
+
+    #[test]
+    fn stream_moves_and_mutes() {
+        let mut session = Session::new();
+        session.update(crate::Message::StreamDeviceChanged(
+            StreamKind::Playback,
+            0,
+            "mock_output.headphones".to_string(),
+        ));
+        session.update(crate::Message::SetStreamMute(
+            StreamKind::Recording,
+            0,
+            true,
+        ));
+        session.run();
+
+        assert_eq!(session.audio.sink_inputs[0].device, 1);
+        assert!(session.audio.source_outputs[0].mute);
+        // moving a stream leaves the default alone
+        assert_eq!(session.default_output(), Some("mock_output.speakers"));
+    }
+
+    #[test]
+    fn polls_often_only_while_watched() {
+        let mut session = Session::new();
+        assert_eq!(session.worker.poll_interval(), Worker::IDLE);
+
+        session.worker.handle([Message::Watch(true)]);
+        assert_eq!(session.worker.poll_interval(), Worker::WATCHING);
+
+        session.running.set(false);
+        session.run();
+        assert_eq!(session.worker.poll_interval(), Backoff::MIN);
+
+        session.running.set(true);
+        session.update(crate::Message::Reconnect);
+        session.run();
+        assert_eq!(session.worker.poll_interval(), Worker::WATCHING);
+
+        session.worker.handle([Message::Watch(false)]);
+        assert_eq!(session.worker.poll_interval(), Worker::IDLE);
+    }
+
+    #[test]
+    fn volume_changes_are_merged() {
+        let mut msgs = Vec::new();
+        let mut volume = ChannelVolumes::default();
+        for step in [10, 20, 30] {
+            volume.set(2, libpulse_binding::volume::Volume(step));
+            queue(&mut msgs, Message::SetSinkInputVolume(0, volume));
+        }
+        queue(&mut msgs, Message::SetSinkInputVolume(1, volume));
+        queue(&mut msgs, Message::GetSinks);
+
+        assert_eq!(
+            msgs,
+            [
+                Message::SetSinkInputVolume(0, volume),
+                Message::SetSinkInputVolume(1, volume),
+                Message::GetSinks,
+            ]
+        );
+    }
+
+    #[test]
+    fn reconnects_after_server_loss() {
+        let mut session = Session::new();
+        session.update(crate::Message::OutputChanged(
+            "mock_output.headphones".to_string(),
+        ));
+        session.run();
+
+        session.running.set(false);
+        session.run();
+        assert!(matches!(
+            session.audio.pulse_state,
+            PulseState::Disconnected(_)
+        ));
+
+        // retrying while the server is down changes nothing
+        session.update(crate::Message::Reconnect);
+        session.run();
+        assert!(matches!(
+            session.audio.pulse_state,
+            PulseState::Disconnected(_)
+        ));
+
+        session.running.set(true);
+        session.update(crate::Message::Reconnect);
+        session.run();
+        assert!(matches!(
+            session.audio.pulse_state,
+            PulseState::Connected(_)
+        ));
+        // everything is listed again from the restarted server
+        assert_eq!(session.audio.outputs.len(), 2);
+        assert_eq!(session.default_output(), Some("mock_output.speakers"));
+        assert!(session.audio.sink_inputs.iter().all(|s| s.device == 0));
+    }
+}
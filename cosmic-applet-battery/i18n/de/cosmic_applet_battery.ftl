@@ -5,7 +5,7 @@ balanced = Ausgeglichen
 balanced-desc = Standardleistung und Akkuverbrauch.
 performance = Hohe Leistung
 performance-desc = Hohe Leistung und Stromverbrauch.
-max-charge = Erhöhe die Lebensdauer deines Akkus, indem du einen maximalen Ladewert von 80 % festlegst.
+max-charge = Erhöhe die Lebensdauer deines Akkus, indem du einen maximalen Ladewert von {$percent} % festlegst.
 seconds = s
 minutes = m
 hours = h
@@ -5,11 +5,82 @@ balanced = Balanced
 balanced-desc = Standard performance and battery usage.
 performance = High Performance
 performance-desc = High performance and power usage.
-max-charge = Increase the lifespan of your battery by setting a maximum charge value of 80%
+max-charge = Increase the lifespan of your battery by setting a maximum charge value of {$percent}%
+charge-range = Charges from {$start}% up to {$end}%
+charge-custom = Custom
+charge-start = Start at
+charge-end = Stop at
 seconds = s
 minutes = m
 hours = h
 until-empty = until empty
+until-full = until full
 power-settings = Power and Battery Settings...
 dgpu-running = Discrete GPU is active and can reduce battery life
-dgpu-applications = Applications using {$gpu_name} discrete GPU
\ No newline at end of file
+dgpu-applications = Applications using {$gpu_name} discrete GPU
+devices = Devices
+level-critical = Critical
+level-low = Low
+level-normal = Normal
+level-high = High
+level-full = Full
+level-unknown = Unknown level
+peripheral-charging = {$level}, charging
+peripheral-low = {$level}, battery low
+history = History
+history-charge = Charge
+history-rate = Power
+history-charge-caption = Battery charge over the last 6 hours
+history-rate-caption = Power draw over the last 6 hours, up to {$watts} W
+notifications = Notifications
+warning-level = Low
+critical-level = Critical
+notify-full = Notify when the battery is charged
+off = Off
+battery-low = Battery low
+battery-low-body = {$percent}% of battery remaining.
+battery-critical = Battery critically low
+battery-critical-body = {$percent}% of battery remaining. Plug in the charger now.
+battery-full = Battery fully charged
+battery-limit-reached = Charge limit reached
+battery-full-body = The battery is charged to {$percent}%.
+battery-details = Battery details
+all-batteries = All batteries
+battery-number = Battery {$number}
+health = Health
+health-value = {$health}% ({$wear}% wear)
+capacity = Capacity
+energy = Energy
+energy-rate = Power draw
+voltage = Voltage
+charge-cycles = Charge cycles
+technology = Technology
+vendor = Vendor
+lithium-ion = Lithium ion
+lithium-polymer = Lithium polymer
+lithium-iron-phosphate = Lithium iron phosphate
+lead-acid = Lead acid
+nickel-cadmium = Nickel cadmium
+nickel-metal-hydride = Nickel metal hydride
+automatic-profiles = Automatic profiles
+rule-ac = When plugged in
+rule-battery = On battery
+rule-low-battery = Power saver below
+rule-unchanged = Don't change
+rule-overridden = Picked by hand until the next automatic switch.
+performance-degraded = High Performance is limited: {$reason}
+performance-inhibited = High Performance is unavailable: {$reason}
+lap-detected = the computer is on a lap
+high-operating-temperature = the computer is too hot
+profile-hold = {$application} requests {$profile}
+unknown-application = An application
+release-all-holds = Release all holds
+graphics-mode = Graphics mode
+graphics-integrated = Integrated
+graphics-hybrid = Hybrid
+graphics-nvidia = NVIDIA
+graphics-compute = Compute
+graphics-pending = Restart or log out to switch to {$mode} graphics.
+restart = Restart
+cancel = Cancel
+dgpu-power-off = Power off discrete GPU when idle
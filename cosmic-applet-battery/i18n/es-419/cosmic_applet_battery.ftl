@@ -5,7 +5,7 @@ balanced = Balanceado
 balanced-desc = Rendimiento y uso de energía estándar.
 performance = Alto rendimiento
 performance-desc = Rendimiento y uso de energía alto.
-max-charge = Aumentar la vida útil de la batería estableciendo un límite de carga del 80 %
+max-charge = Aumentar la vida útil de la batería estableciendo un límite de carga del {$percent} %
 seconds = s
 minutes = m
 hours = h
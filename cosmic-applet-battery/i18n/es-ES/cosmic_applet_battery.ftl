@@ -5,7 +5,7 @@ balanced = Balanceado
 balanced-desc = Rendimiento y consumo de alimentación normales.
 performance = Rendimiento alto
 performance-desc = Rendimiento y consumo de alimentación aumentados.
-max-charge = Aumentar la vida útil de la batería al poner un límite de carga del 80%
+max-charge = Aumentar la vida útil de la batería al poner un límite de carga del {$percent}%
 seconds = s
 minutes = m
 hours = h
@@ -5,7 +5,7 @@ balanced = Tasapainoitettu
 balanced-desc = Normaali suorituskyky ja akun käyttö.
 performance = Korkea suorituskyky
 performance-desc = Korkea suorituskyky ja akun käyttö.
-max-charge = Pidennä akun käyttöikää asettamalla enimmäislatausarvoksi 80 %
+max-charge = Pidennä akun käyttöikää asettamalla enimmäislatausarvoksi {$percent} %
 seconds = s
 minutes = m
 hours = t
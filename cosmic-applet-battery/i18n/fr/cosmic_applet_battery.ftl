@@ -5,7 +5,7 @@ balanced = Équilibré
 balanced-desc = Performance et usage de la batterie standard.
 performance = Hautes performances
 performance-desc = Performances et consommation élevées.
-max-charge = Augmente la durée de vie de votre batterie en mettant la charge maximale à 80%
+max-charge = Augmente la durée de vie de votre batterie en mettant la charge maximale à {$percent}%
 seconds = s
 minutes = m
 hours = h
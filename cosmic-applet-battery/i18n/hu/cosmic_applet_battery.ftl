@@ -5,7 +5,7 @@ balanced = Kiegyensúlyozott
 balanced-desc = Normál teljesítmény és akkumulátorhasználat.
 performance = Nagy teljesítmény
 performance-desc = Nagy teljesítmény és energiafogyasztás.
-max-charge = Növelje az akkumulátor élettartamát a maximális töltési érték 80%-os beállításával
+max-charge = Növelje az akkumulátor élettartamát a maximális töltési érték {$percent}%-os beállításával
 seconds = s
 minutes = m
 hours = h
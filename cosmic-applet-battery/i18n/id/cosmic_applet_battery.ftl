@@ -5,7 +5,7 @@ balanced = Seimbang
 balanced-desc = Kinerja dan penggunaan baterai standar.
 performance = Kinerja Tinggi
 performance-desc = Kinerja dan penggunaan daya yang tinggi.
-max-charge = Tingkatkan masa pakai baterai Anda dengan mengatur nilai pengisian maksimum 80%
+max-charge = Tingkatkan masa pakai baterai Anda dengan mengatur nilai pengisian maksimum {$percent}%
 seconds = d
 minutes = m  
 hours = j
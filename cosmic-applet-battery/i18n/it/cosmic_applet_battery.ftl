@@ -5,7 +5,7 @@ balanced = Bilanciato
 balanced-desc = Consumi e performance standard.
 performance = Performance
 performance-desc = Consumi e performance alte.
-max-charge = Migliora la durata di vita della batteria caricandola fino all'80%
+max-charge = Migliora la durata di vita della batteria caricandola fino all'{$percent}%
 seconds = s
 minutes = m
 hours = h
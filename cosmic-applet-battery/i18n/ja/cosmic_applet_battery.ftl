@@ -5,7 +5,7 @@ balanced = バランス
 balanced-desc = 性能と消費は通常になります。
 performance = ハイパフォーマンス
 performance-desc = 性能と消費は高くなります。
-max-charge = バッテリーの寿命を増すために80％だけまで充電する
+max-charge = バッテリーの寿命を増すために{$percent}％だけまで充電する
 seconds = 秒
 minutes = 分
 hours = 時間
@@ -5,7 +5,7 @@ balanced = Zbalansowany
 balanced-desc = Standardowa wydajność i zużycie baterii.
 performance = Wysoko Wydajny
 performance-desc = Wysoka wydajność i zwiększone zużycie energii.
-max-charge = Zwiększ żywotność swojej baterii ustawiając maksymalny poziom naładowania na 80%.
+max-charge = Zwiększ żywotność swojej baterii ustawiając maksymalny poziom naładowania na {$percent}%.
 seconds = s
 minutes = m
 hours = g
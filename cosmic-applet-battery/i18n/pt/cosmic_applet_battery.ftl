@@ -5,7 +5,7 @@ balanced = Equilibrado
 balanced-desc = Desempenho padrão e uso de bateria.
 performance = Alto desempenho
 performance-desc = Alto desempenho e uso de energia.
-max-charge = Aumente a vida útil da sua bateria definindo um valor máximo de carga de 80%
+max-charge = Aumente a vida útil da sua bateria definindo um valor máximo de carga de {$percent}%
 seconds = s
 minutes = m
 hours = h
@@ -5,7 +5,7 @@ balanced = Сбалансированный
 balanced-desc = Стандартные производительность и расход батареи.
 performance = Высокая производительность
 performance-desc = Повышенные производительность и энергопотребление.
-max-charge = Увеличить срок службы аккумулятора за счёт ограничения максимального значения заряда до 80%
+max-charge = Увеличить срок службы аккумулятора за счёт ограничения максимального значения заряда до {$percent}%
 seconds = с.
 minutes = мин.
 hours = ч.
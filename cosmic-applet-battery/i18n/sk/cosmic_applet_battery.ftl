@@ -5,7 +5,7 @@ balanced = Vyvážený
 balanced-desc = Štandardný výkon a spotreba energie.
 performance = Vysoký výkon
 performance-desc = Vysoký výkon a spotreba energie.
-max-charge = Predlžiť životnosť batérie nastavením maximálnej úrovne nabitia na 80%
+max-charge = Predlžiť životnosť batérie nastavením maximálnej úrovne nabitia na {$percent}%
 seconds = s
 minutes = m
 hours = h
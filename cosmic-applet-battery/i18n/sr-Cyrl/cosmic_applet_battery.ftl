@@ -5,7 +5,7 @@ balanced = Балансирано
 balanced-desc = Стандардне перформансе и потрошња батерије.
 performance = Високе перформансе
 performance-desc = Високе перформансе и потрошња батерије.
-max-charge = Продужити животни век батерије постављајући њен лимит пуњења на 80%.
+max-charge = Продужити животни век батерије постављајући њен лимит пуњења на {$percent}%.
 seconds = сек.
 minutes = мин.
 hours = сат.
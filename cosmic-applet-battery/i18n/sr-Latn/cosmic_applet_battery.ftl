@@ -5,7 +5,7 @@ balanced = Balansirano
 balanced-desc = Standardne performanse i potrošnja baterije.
 performance = Visoke performanse
 performance-desc = Visoke performanse i potrošnja baterije.
-max-charge = Produžiti životni vek baterije postavljajući njen limit punjenja na 80%.
+max-charge = Produžiti životni vek baterije postavljajući njen limit punjenja na {$percent}%.
 seconds = sek.
 minutes = min.
 hours = sat.
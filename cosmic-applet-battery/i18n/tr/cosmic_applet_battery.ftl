@@ -5,7 +5,7 @@ balanced = Dengeli
 balanced-desc = Standart başarım ve güç tüketimi.
 performance = Yüksek başarım
 performance-desc = Yüksek başarım ve güç tüketimi.
-max-charge = En yüksek şarj değerini %80 olarak ayarlayarak pilinizin ömrünü artırın
+max-charge = En yüksek şarj değerini %{$percent} olarak ayarlayarak pilinizin ömrünü artırın
 seconds = sn
 minutes = dk
 hours = sa
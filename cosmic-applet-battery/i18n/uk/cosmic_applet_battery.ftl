@@ -5,7 +5,7 @@ balanced = Збалансовано
 balanced-desc = Стандарт продуктивності та споживання акумулятора.
 performance = Висока продуктивність
 performance-desc = Підвищення продуктивності та споживання енергії.
-max-charge = Збільште термін служби акумулятора, обмеживши заряджання до 80%
+max-charge = Збільште термін служби акумулятора, обмеживши заряджання до {$percent}%
 seconds = сек
 minutes = хв
 hours = год
@@ -5,7 +5,7 @@ balanced = 平衡
 balanced-desc = 标准性能和电池使用
 performance = 高性能
 performance-desc = 高性能和功耗
-max-charge = 最多充电到 80% 以增加电池寿命
+max-charge = 最多充电到 {$percent}% 以增加电池寿命
 seconds = s
 minutes = m
 hours = h
@@ -3,12 +3,24 @@
 
 use crate::{
     backend::{
-        get_charging_limit, power_profile_subscription, set_charging_limit, Power,
-        PowerProfileRequest, PowerProfileUpdate,
+        boot_id, custom_charge_thresholds, get_charge_profiles, get_charging_limit, get_energy,
+        get_graphics, get_profile_status, power_off_graphics, power_profile_subscription,
+        release_profile_holds, restart, set_charging_limit, set_graphics, ChargeProfile,
+        ChargeThresholds, Energy, Graphics, Power, PowerProfileRequest, PowerProfileUpdate,
+        ProfileStatus,
     },
-    config,
-    dgpu::{dgpu_subscription, Entry, GpuUpdate},
+    config::{self, BatteryAppletConfig},
+    details::{details_view, get_details, Details},
+    dgpu::{
+        desktop_exec, dgpu_subscription, integrated_gpu_env, matches_app_id, terminate,
+        wait_for_exit, Entry, GpuUpdate,
+    },
+    estimator::Estimator,
     fl,
+    history::{get_history, history_view, HistoryKind},
+    notifications::{self, Notification, Urgency},
+    peripherals::{peripheral_subscription, Charge, Peripheral},
+    wayland_subscription::{wayland_subscription, ToplevelRequest, ToplevelUpdate, WaylandUpdate},
 };
 use cosmic::{
     applet::{
@@ -16,7 +28,11 @@ use cosmic::{
         menu_button, padded_control,
         token::subscription::{activation_token_subscription, TokenRequest, TokenUpdate},
     },
-    cctk::sctk::reexports::calloop,
+    cctk::{
+        cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1,
+        sctk::reexports::calloop, toplevel_info::ToplevelInfo,
+    },
+    cosmic_config::CosmicConfigEntry,
     iced::{
         alignment::Horizontal,
         wayland::popup::{destroy_popup, get_popup},
@@ -27,7 +43,7 @@ use cosmic::{
     iced_runtime::core::layout::Limits,
     iced_style::application,
     iced_widget::{Column, Row},
-    widget::{divider, horizontal_space, icon, scrollable, text, vertical_space},
+    widget::{button, divider, dropdown, horizontal_space, icon, scrollable, text, vertical_space},
     Command, Element, Theme,
 };
 use cosmic_settings_subscriptions::{
@@ -41,11 +57,10 @@ use cosmic_settings_subscriptions::{
 };
 use cosmic_time::{anim, chain, id, once_cell::sync::Lazy, Instant, Timeline};
 
-use std::{collections::HashMap, path::PathBuf, time::Duration};
+use std::{collections::HashMap, io, path::PathBuf, time::Duration};
 use tokio::sync::mpsc::UnboundedSender;
 
 // XXX improve
-// TODO: time to empty varies? needs averaging?
 fn format_duration(duration: Duration) -> String {
     let secs = duration.as_secs();
     if secs > 60 {
@@ -65,6 +80,119 @@ pub fn run() -> cosmic::iced::Result {
 }
 
 static MAX_CHARGE: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
+static DGPU_POWER_OFF: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
+
+// Buttons to quit a process using the dGPU, or to start it again on the
+// integrated GPU
+fn gpu_app_actions(app: &Entry) -> Element<'_, Message> {
+    if app.pid.is_none() {
+        return horizontal_space(1.0).into();
+    }
+    let action = |name: &'static str, message: Message| {
+        button::icon(icon::from_name(name).size(16).symbolic(true))
+            .style(cosmic::theme::Button::Icon)
+            .on_press(message)
+    };
+
+    let mut actions = row![].spacing(4).align_items(Alignment::Center);
+    if app.exec.is_some() {
+        actions = actions.push(action(
+            "view-refresh-symbolic",
+            Message::RelaunchGpuApp(app.clone()),
+        ));
+    }
+    actions
+        .push(action(
+            "window-close-symbolic",
+            Message::QuitGpuApp(app.clone()),
+        ))
+        .into()
+}
+
+// Header of a section that opens below it when pressed
+pub fn revealer_header<'a>(title: String, open: bool, message: Message) -> Element<'a, Message> {
+    menu_button(
+        row![
+            text::body(title)
+                .width(Length::Fill)
+                .vertical_alignment(Vertical::Center),
+            container(
+                icon::from_name(if open {
+                    "go-down-symbolic"
+                } else {
+                    "go-up-symbolic"
+                })
+                .size(14)
+                .symbolic(true)
+            )
+            .align_x(Horizontal::Center)
+            .align_y(Vertical::Center)
+            .width(Length::Fixed(24.0))
+            .height(Length::Fixed(24.0)),
+        ]
+        .align_items(Alignment::Center),
+    )
+    .on_press(message)
+    .into()
+}
+
+fn graphics_name(graphics: Graphics) -> String {
+    match graphics {
+        Graphics::Integrated => fl!("graphics-integrated"),
+        Graphics::Hybrid => fl!("graphics-hybrid"),
+        Graphics::Nvidia => fl!("graphics-nvidia"),
+        Graphics::Compute => fl!("graphics-compute"),
+    }
+}
+
+/// A condition under which the power profile is switched automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProfileRule {
+    Ac,
+    Battery,
+    LowBattery,
+}
+
+// Profiles offered for rules, `None` leaving the profile as it is
+const RULE_PROFILES: [Option<Power>; 4] = [
+    None,
+    Some(Power::Battery),
+    Some(Power::Balanced),
+    Some(Power::Performance),
+];
+
+/// A state of the battery that users are notified about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BatteryAlert {
+    Warning,
+    Critical,
+    Full,
+}
+
+// Icon levels, along with the percentage above which each is shown
+const BATTERY_LEVELS: [(u8, f64); 8] = [
+    (100, 95.0),
+    (90, 80.0),
+    (80, 65.0),
+    (50, 35.0),
+    (35, 20.0),
+    (20, 14.0),
+    (10, 9.0),
+    (5, 5.0),
+];
+
+// Levels that have an icon for a limited battery
+const LIMITED_BATTERY_LEVELS: [u8; 8] = [0, 5, 10, 20, 35, 50, 65, 80];
+
+// Lowest charge limit offered for custom thresholds
+const MIN_CHARGE_END: u8 = 50;
+
+// Keep thresholds that were saved or come from the daemon in the range the
+// sliders offer, with charging starting below where it stops
+fn clamp_thresholds((start, end): ChargeThresholds) -> ChargeThresholds {
+    let end = end.clamp(MIN_CHARGE_END, 100);
+    (start.min(end.saturating_sub(1)), end)
+}
 
 #[derive(Clone, Default)]
 struct GPUData {
@@ -76,13 +204,36 @@ struct GPUData {
 #[derive(Clone, Default)]
 struct CosmicBatteryApplet {
     core: cosmic::app::Core,
+    config: BatteryAppletConfig,
     icon_name: String,
     display_icon_name: String,
-    charging_limit: bool,
+    charging_limit: Option<ChargeThresholds>,
+    charge_profiles: Vec<ChargeProfile>,
+    custom_charge: bool,
+    custom_thresholds: bool,
     battery_percent: f64,
     on_battery: bool,
     gpus: HashMap<PathBuf, GPUData>,
-    time_remaining: Duration,
+    peripherals: Vec<Peripheral>,
+    history_kind: HistoryKind,
+    history: Vec<Option<f64>>,
+    show_details: bool,
+    details: Option<Details>,
+    profile_status: Option<ProfileStatus>,
+    graphics: Option<Graphics>,
+    // mode of the next boot when the applet started
+    session_graphics: Option<Graphics>,
+    boot_id: Option<String>,
+    graphics_options: Vec<String>,
+    confirm_restart: bool,
+    confirm_release: bool,
+    estimator: Estimator,
+    alert: Option<BatteryAlert>,
+    // the battery charged while the applet was running, so that it doesn't
+    // tell about a full battery at every login
+    charged: bool,
+    notification_id: u32,
+    notification_settings: bool,
     max_kbd_brightness: Option<i32>,
     kbd_brightness: Option<i32>,
     max_screen_brightness: Option<i32>,
@@ -92,8 +243,20 @@ struct CosmicBatteryApplet {
     kbd_sender: Option<UnboundedSender<KeyboardBacklightRequest>>,
     power_profile: Power,
     power_profile_sender: Option<UnboundedSender<PowerProfileRequest>>,
+    // rule in effect, with the profile it switched to
+    active_rule: Option<(ProfileRule, Power)>,
+    // profile from before the rules switched it, restored once none applies
+    rule_restore: Option<Power>,
+    // a profile was picked by hand while a rule applied
+    rule_overridden: bool,
+    show_rules: bool,
+    rule_options: Vec<String>,
     timeline: Timeline,
     token_tx: Option<calloop::channel::Sender<TokenRequest>>,
+    // environment of applications to launch on the integrated GPU, by command
+    pending_launches: HashMap<String, Vec<(String, String)>>,
+    wayland_tx: Option<calloop::channel::Sender<ToplevelRequest>>,
+    toplevels: Vec<(ZcosmicToplevelHandleV1, ToplevelInfo)>,
     zbus_connection: Option<zbus::Connection>,
 }
 
@@ -102,26 +265,25 @@ impl CosmicBatteryApplet {
         percent = percent.clamp(0.0, 100.0);
         self.on_battery = on_battery;
         self.battery_percent = percent;
-        let battery_percent = if self.battery_percent > 95.0 && !self.charging_limit {
-            100
-        } else if self.battery_percent > 80.0 && !self.charging_limit {
-            90
-        } else if self.battery_percent > 65.0 {
-            80
-        } else if self.battery_percent > 35.0 {
-            50
-        } else if self.battery_percent > 20.0 {
-            35
-        } else if self.battery_percent > 14.0 {
-            20
-        } else if self.battery_percent > 9.0 {
-            10
-        } else if self.battery_percent > 5.0 {
-            5
+        let mut battery_percent = BATTERY_LEVELS
+            .iter()
+            .find(|(_, above)| self.battery_percent > *above)
+            .map_or(0, |(level, _)| *level);
+        if let Some((_, end)) = self.charging_limit {
+            // the battery never goes past the limit, so neither does the icon
+            let max_level = LIMITED_BATTERY_LEVELS
+                .iter()
+                .rev()
+                .find(|level| **level <= end)
+                .copied()
+                .unwrap_or_default();
+            battery_percent = battery_percent.min(max_level);
+        }
+        let limited = if self.charging_limit.is_some() {
+            "limited-"
         } else {
-            0
+            ""
         };
-        let limited = if self.charging_limit { "limited-" } else { "" };
         let charging = if on_battery { "" } else { "charging-" };
         self.icon_name =
             format!("cosmic-applet-battery-level-{battery_percent}-{limited}{charging}symbolic",);
@@ -154,29 +316,613 @@ impl CosmicBatteryApplet {
             format!("cosmic-applet-battery-display-brightness-{screen_brightness}-symbolic",);
     }
 
-    fn set_charging_limit(&mut self, limit: bool) {
+    fn set_power_profile(&mut self, profile: Power) {
+        if let Some(tx) = self.power_profile_sender.as_ref() {
+            let _ = tx.send(PowerProfileRequest::Set(profile));
+            self.power_profile = profile;
+        }
+    }
+
+    fn profile_rule(&self) -> Option<(ProfileRule, Power)> {
+        if !self.on_battery {
+            return self.config.ac_profile.map(|p| (ProfileRule::Ac, p));
+        }
+        let level = self.config.power_saver_level;
+        if level > 0 && self.battery_percent <= f64::from(level) {
+            Some((ProfileRule::LowBattery, Power::Battery))
+        } else {
+            self.config
+                .battery_profile
+                .map(|p| (ProfileRule::Battery, p))
+        }
+    }
+
+    // Switch profiles when the condition of a rule starts or ends, or its
+    // profile is changed. Rules only act on changes, so a profile picked by
+    // hand stays until the next one.
+    fn apply_profile_rules(&mut self) {
+        if self.power_profile_sender.is_none() {
+            return;
+        }
+        let rule = self.profile_rule();
+        if rule == self.active_rule {
+            return;
+        }
+
+        self.rule_overridden = false;
+        match rule {
+            Some((_, profile)) => {
+                if self.active_rule.is_none() {
+                    self.rule_restore = Some(self.power_profile);
+                }
+                self.active_rule = rule;
+                self.set_power_profile(profile);
+            }
+            None => {
+                self.active_rule = None;
+                if let Some(profile) = self.rule_restore.take() {
+                    self.set_power_profile(profile);
+                }
+            }
+        }
+    }
+
+    fn rules_view(&self) -> Element<Message> {
+        let selected = |profile: Option<Power>| RULE_PROFILES.iter().position(|p| *p == profile);
+        let rule = |label: String, profile: Option<Power>, message: fn(usize) -> Message| {
+            padded_control(
+                row![
+                    text::body(label).width(Length::Fill),
+                    dropdown(&self.rule_options, selected(profile), message)
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            )
+        };
+        let level = self.config.power_saver_level;
+
+        let mut content = column![
+            rule(
+                fl!("rule-ac"),
+                self.config.ac_profile,
+                Message::SetAcProfile
+            ),
+            rule(
+                fl!("rule-battery"),
+                self.config.battery_profile,
+                Message::SetBatteryProfile
+            ),
+            padded_control(
+                row![
+                    text::body(fl!("rule-low-battery")).width(Length::Fixed(120.0)),
+                    slider(0..=50, level, Message::SetPowerSaverLevel)
+                        .on_release(Message::SaveConfig),
+                    text(if level == 0 {
+                        fl!("off")
+                    } else {
+                        format!("{level}%")
+                    })
+                    .size(16)
+                    .width(Length::Fixed(40.0))
+                    .horizontal_alignment(Horizontal::Right)
+                ]
+                .spacing(12)
+                .align_items(Alignment::Center),
+            ),
+        ];
+        if self.rule_overridden {
+            content = content.push(padded_control(text::caption(fl!("rule-overridden"))));
+        }
+        content.into()
+    }
+
+    fn set_charging_limit(&mut self, limit: Option<ChargeThresholds>) {
         self.charging_limit = limit;
         self.update_battery(self.battery_percent, self.on_battery);
     }
+
+    // Percentage at which the battery stops charging
+    fn full_percent(&self) -> f64 {
+        self.charging_limit.map_or(100.0, |(_, end)| f64::from(end))
+    }
+
+    fn battery_alert(&self) -> Option<BatteryAlert> {
+        let percent = self.battery_percent;
+        if self.on_battery {
+            let level = |level: u8| level > 0 && percent <= f64::from(level);
+            if level(self.config.critical_level) {
+                Some(BatteryAlert::Critical)
+            } else if level(self.config.warning_level) {
+                Some(BatteryAlert::Warning)
+            } else {
+                None
+            }
+        } else {
+            let full = self.full_percent();
+            (self.config.notify_full && self.charged && percent >= full - 1.0)
+                .then_some(BatteryAlert::Full)
+        }
+    }
+
+    // Notify when the battery enters a new state, replacing the previous
+    // notification, and withdraw it once the state is over
+    fn update_alert(&mut self) -> cosmic::iced::Command<cosmic::app::Message<Message>> {
+        let full = self.full_percent();
+        // the charge may settle just below the limit
+        if self.on_battery {
+            self.charged = false;
+        } else if self.battery_percent < full - 1.0 {
+            self.charged = true;
+        }
+
+        let alert = self.battery_alert();
+        if alert == self.alert {
+            return Command::none();
+        }
+        let Some(conn) = self.zbus_connection.clone() else {
+            return Command::none();
+        };
+        self.alert = alert;
+
+        let percent = format!("{:.0}", self.battery_percent);
+        let notification = match alert {
+            Some(BatteryAlert::Warning) => Notification {
+                summary: fl!("battery-low"),
+                body: fl!("battery-low-body", percent = percent),
+                icon: "battery-caution-symbolic".to_string(),
+                urgency: Urgency::Normal,
+            },
+            Some(BatteryAlert::Critical) => Notification {
+                summary: fl!("battery-critical"),
+                body: fl!("battery-critical-body", percent = percent),
+                icon: "battery-empty-symbolic".to_string(),
+                urgency: Urgency::Critical,
+            },
+            Some(BatteryAlert::Full) => Notification {
+                summary: if self.charging_limit.is_some() {
+                    fl!("battery-limit-reached")
+                } else {
+                    fl!("battery-full")
+                },
+                body: fl!("battery-full-body", percent = percent),
+                icon: "battery-full-charged-symbolic".to_string(),
+                urgency: Urgency::Low,
+            },
+            None => {
+                let id = std::mem::take(&mut self.notification_id);
+                if id == 0 {
+                    return Command::none();
+                }
+                return cosmic::iced::Command::perform(notifications::close(conn, id), |_| {
+                    cosmic::app::Message::None
+                });
+            }
+        };
+
+        cosmic::iced::Command::perform(
+            notifications::notify(conn, self.notification_id, notification),
+            |id| cosmic::app::Message::App(Message::Notified(id)),
+        )
+    }
+
+    fn write_config(&self) {
+        if let Ok(helper) =
+            cosmic::cosmic_config::Config::new(config::APP_ID, BatteryAppletConfig::VERSION)
+        {
+            if let Err(err) = self.config.write_entry(&helper) {
+                tracing::error!(?err, "Error writing config");
+            }
+        }
+    }
+
+    fn peripheral_view<'a>(&self, peripheral: &'a Peripheral) -> Element<'a, Message> {
+        let warn = self.config.peripheral_warnings.contains(&peripheral.id);
+        let level = match peripheral.charge {
+            Charge::Percent(percent) => format!("{percent:.0}%"),
+            Charge::Critical => fl!("level-critical"),
+            Charge::Low => fl!("level-low"),
+            Charge::Normal => fl!("level-normal"),
+            Charge::High => fl!("level-high"),
+            Charge::Full => fl!("level-full"),
+            Charge::Unknown => fl!("level-unknown"),
+        };
+        let low = warn && !peripheral.charging && peripheral.charge.is_low();
+        let description = if peripheral.charging {
+            fl!("peripheral-charging", level = level)
+        } else if low {
+            fl!("peripheral-low", level = level)
+        } else {
+            level
+        };
+
+        padded_control(
+            row![
+                icon::from_name(peripheral.kind.icon_name())
+                    .size(24)
+                    .symbolic(true),
+                column![text::body(&peripheral.name), text::caption(description)]
+                    .width(Length::Fill),
+                if low {
+                    container(
+                        icon::from_name("dialog-warning-symbolic")
+                            .size(16)
+                            .symbolic(true),
+                    )
+                } else {
+                    container(horizontal_space(1.0))
+                },
+                button::icon(
+                    icon::from_name(if warn {
+                        "notification-alert-symbolic"
+                    } else {
+                        "notification-disabled-symbolic"
+                    })
+                    .size(16)
+                    .symbolic(true),
+                )
+                .style(cosmic::theme::Button::Icon)
+                .on_press(Message::SetPeripheralWarning(peripheral.id.clone(), !warn)),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+        )
+        .into()
+    }
+
+    fn refresh_history(&self) -> cosmic::iced::Command<cosmic::app::Message<Message>> {
+        let kind = self.history_kind;
+        cosmic::iced::Command::perform(get_history(kind), move |history| {
+            cosmic::app::Message::App(Message::History(kind, history))
+        })
+    }
+
+    fn refresh_details(&self) -> cosmic::iced::Command<cosmic::app::Message<Message>> {
+        cosmic::iced::Command::perform(get_details(), |details| {
+            cosmic::app::Message::App(Message::Details(details))
+        })
+    }
+
+    fn refresh_profile_status(&self) -> cosmic::iced::Command<cosmic::app::Message<Message>> {
+        cosmic::iced::Command::perform(get_profile_status(), |status| {
+            cosmic::app::Message::App(Message::ProfileStatus(status))
+        })
+    }
+
+    fn profile_status_view(&self, status: &ProfileStatus) -> Vec<Element<Message>> {
+        let mut content: Vec<Element<Message>> = Vec::new();
+
+        if !status.inhibited.is_empty() {
+            content.push(
+                padded_control(text::caption(fl!(
+                    "performance-inhibited",
+                    reason = status.inhibited.as_str()
+                )))
+                .into(),
+            );
+        }
+        for reason in &status.degraded {
+            let reason = match reason.as_str() {
+                "lap-detected" => fl!("lap-detected"),
+                "high-operating-temperature" => fl!("high-operating-temperature"),
+                reason => reason.to_string(),
+            };
+            content.push(
+                padded_control(text::caption(fl!("performance-degraded", reason = reason))).into(),
+            );
+        }
+
+        if !status.holds.is_empty() {
+            let holds = status.holds.iter().map(|hold| {
+                let profile = match hold.profile {
+                    Power::Battery => fl!("battery"),
+                    Power::Balanced => fl!("balanced"),
+                    Power::Performance => fl!("performance"),
+                };
+                let application = if hold.application_id.is_empty() {
+                    fl!("unknown-application")
+                } else {
+                    hold.application_id.clone()
+                };
+                let mut hold_content = column![text::body(fl!(
+                    "profile-hold",
+                    application = application,
+                    profile = profile
+                ))];
+                if !hold.reason.is_empty() {
+                    hold_content = hold_content.push(text::caption(hold.reason.clone()));
+                }
+                hold_content.into()
+            });
+            // picking a profile drops the holds of every application
+            let release: Element<_> = if self.confirm_release {
+                row![
+                    button::text(fl!("cancel"))
+                        .style(cosmic::theme::Button::Standard)
+                        .on_press(Message::ConfirmRelease(false)),
+                    button::text(fl!("release-all-holds"))
+                        .style(cosmic::theme::Button::Destructive)
+                        .on_press(Message::ReleaseHolds),
+                ]
+                .spacing(8)
+                .into()
+            } else {
+                button::text(fl!("release-all-holds"))
+                    .style(cosmic::theme::Button::Standard)
+                    .on_press(Message::ConfirmRelease(true))
+                    .into()
+            };
+            content.push(
+                padded_control(
+                    row![
+                        Column::with_children(holds.collect::<Vec<Element<Message>>>())
+                            .spacing(4)
+                            .width(Length::Fill),
+                        release,
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+        content
+    }
+
+    // Close the windows of an application the way the app list does, or ask
+    // the process to quit when it has none or they can't be told apart from
+    // those of other applications. Returns the app id of its windows.
+    fn quit_gpu_app(&self, app: &Entry) -> io::Result<Option<String>> {
+        let pid = app.pid.ok_or(io::ErrorKind::InvalidInput)?;
+        let mut app_ids = self
+            .toplevels
+            .iter()
+            .map(|(_, info)| info.app_id.as_str())
+            .filter(|app_id| {
+                app.executable
+                    .as_deref()
+                    .is_some_and(|executable| matches_app_id(executable, app_id))
+            })
+            .collect::<Vec<_>>();
+        app_ids.sort_unstable();
+        app_ids.dedup();
+        match (self.wayland_tx.as_ref(), app_ids.as_slice()) {
+            (Some(tx), [app_id]) => {
+                for (handle, _) in self.toplevels.iter().filter(|(_, i)| i.app_id == *app_id) {
+                    let _ = tx.send(ToplevelRequest::Close(handle.clone()));
+                }
+                Ok(Some(app_id.to_string()))
+            }
+            _ => terminate(pid).map(|()| None),
+        }
+    }
+
+    // Mode the graphics run in until the next restart
+    fn running_graphics(&self) -> Option<Graphics> {
+        match &self.config.graphics_session {
+            Some((boot, graphics)) if self.boot_id.as_ref() == Some(boot) => Some(*graphics),
+            _ => self.session_graphics,
+        }
+    }
+
+    fn graphics_view(&self, graphics: Graphics) -> Element<Message> {
+        let selected = Graphics::ALL.iter().position(|g| *g == graphics);
+        let mut content = column![padded_control(
+            row![
+                text::body(fl!("graphics-mode")).width(Length::Fill),
+                dropdown(&self.graphics_options, selected, Message::SelectGraphics)
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+        )];
+
+        if self.running_graphics().is_some_and(|g| g != graphics) {
+            let restart: Element<_> = if self.confirm_restart {
+                row![
+                    button::text(fl!("cancel"))
+                        .style(cosmic::theme::Button::Standard)
+                        .on_press(Message::ConfirmRestart(false)),
+                    button::text(fl!("restart"))
+                        .style(cosmic::theme::Button::Destructive)
+                        .on_press(Message::Restart),
+                ]
+                .spacing(8)
+                .into()
+            } else {
+                button::text(fl!("restart"))
+                    .style(cosmic::theme::Button::Standard)
+                    .on_press(Message::ConfirmRestart(true))
+                    .into()
+            };
+            content = content.push(padded_control(
+                row![
+                    text::caption(fl!("graphics-pending", mode = graphics_name(graphics)))
+                        .width(Length::Fill),
+                    restart
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ));
+        }
+
+        content
+            .push(padded_control(
+                anim!(
+                    DGPU_POWER_OFF,
+                    &self.timeline,
+                    fl!("dgpu-power-off"),
+                    self.config.dgpu_power_off,
+                    Message::SetDgpuPowerOff,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            ))
+            .into()
+    }
+
+    // the limit is read back, as it may not be applied as requested
+    fn apply_charging_limit(&self) -> cosmic::iced::Command<cosmic::app::Message<Message>> {
+        let limit = self.charging_limit;
+        cosmic::iced::Command::perform(
+            async move {
+                if let Err(err) = set_charging_limit(limit).await {
+                    tracing::error!("Failed to set charging limit: {}", err);
+                }
+                get_charging_limit().await
+            },
+            |limit| cosmic::app::Message::App(Message::InitChargingLimit(limit)),
+        )
+    }
+
+    fn charge_profile_view(&self, thresholds: ChargeThresholds) -> Element<Message> {
+        let custom = self.custom_thresholds
+            && (self.custom_charge
+                || !self
+                    .charge_profiles
+                    .iter()
+                    .any(|profile| profile.thresholds == thresholds));
+        let selected = |selected: bool| {
+            if selected {
+                container(
+                    icon::from_name("emblem-ok-symbolic")
+                        .size(12)
+                        .symbolic(true),
+                )
+            } else {
+                container(horizontal_space(1.0))
+            }
+        };
+
+        let mut content = Vec::new();
+        for profile in self.charge_profiles.iter().filter(|p| p.is_limited()) {
+            let (start, end) = profile.thresholds;
+            content.push(
+                menu_button(
+                    row![
+                        column![
+                            text::body(&profile.title),
+                            text::caption(fl!("charge-range", start = start, end = end))
+                        ]
+                        .width(Length::Fill),
+                        selected(!custom && profile.thresholds == thresholds)
+                    ]
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Message::SelectChargeProfile(profile.thresholds))
+                .into(),
+            );
+        }
+        // without direct access, UPower only toggles the thresholds it is
+        // configured with
+        if self.custom_thresholds {
+            content.push(
+                menu_button(
+                    row![
+                        text::body(fl!("charge-custom")).width(Length::Fill),
+                        selected(custom)
+                    ]
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Message::CustomChargeLimit)
+                .into(),
+            );
+        }
+
+        if custom {
+            let (start, end) = thresholds;
+            content.push(
+                padded_control(
+                    row![
+                        text::body(fl!("charge-start")).width(Length::Fixed(80.0)),
+                        slider(0..=end.saturating_sub(1), start, Message::SetChargeStart)
+                            .on_release(Message::ApplyChargingLimit),
+                        text(format!("{start}%"))
+                            .size(16)
+                            .width(Length::Fixed(40.0))
+                            .horizontal_alignment(Horizontal::Right)
+                    ]
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+            content.push(
+                padded_control(
+                    row![
+                        text::body(fl!("charge-end")).width(Length::Fixed(80.0)),
+                        slider(MIN_CHARGE_END..=99, end, Message::SetChargeEnd)
+                            .on_release(Message::ApplyChargingLimit),
+                        text(format!("{end}%"))
+                            .size(16)
+                            .width(Length::Fixed(40.0))
+                            .horizontal_alignment(Horizontal::Right)
+                    ]
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+
+        Column::with_children(content).into()
+    }
 }
 
 #[derive(Debug, Clone)]
-enum Message {
+pub enum Message {
     TogglePopup,
     CloseRequested(window::Id),
     SetKbdBrightness(i32),
     SetScreenBrightness(i32),
-    InitChargingLimit(bool),
+    InitChargingLimit(Option<ChargeThresholds>),
+    InitChargeProfiles(Vec<ChargeProfile>),
+    InitCustomThresholds(bool),
     SetChargingLimit(chain::Toggler, bool),
+    SelectChargeProfile(ChargeThresholds),
+    CustomChargeLimit,
+    SetChargeStart(u8),
+    SetChargeEnd(u8),
+    ApplyChargingLimit,
     KeyboardBacklight(KeyboardBacklightUpdate),
     UpowerDevice(DeviceDbusEvent),
+    Wayland(WaylandUpdate),
+    BatterySample(f64, bool, Duration, Option<Energy>),
     GpuOn(PathBuf, String, Option<Vec<Entry>>),
     GpuOff(PathBuf),
     ToggleGpuApps(PathBuf),
+    QuitGpuApp(Entry),
+    RelaunchGpuApp(Entry),
+    LaunchOnIntegrated(String, Vec<(String, String)>),
+    Peripherals(Vec<Peripheral>),
+    SelectHistory(HistoryKind),
+    Refresh,
+    History(HistoryKind, Vec<Option<f64>>),
+    ToggleDetails,
+    Details(Option<Details>),
+    ProfileStatus(Option<ProfileStatus>),
+    InitGraphics(Option<Graphics>),
+    Graphics(Option<Graphics>),
+    SelectGraphics(usize),
+    Restart,
+    ConfirmRestart(bool),
+    SetDgpuPowerOff(chain::Toggler, bool),
+    ConfirmRelease(bool),
+    ReleaseHolds,
+    SetPeripheralWarning(String, bool),
+    ConfigChanged(BatteryAppletConfig),
+    ToggleNotificationSettings,
+    SetWarningLevel(u8),
+    SetCriticalLevel(u8),
+    SetNotifyFull(chain::Toggler, bool),
+    SaveConfig,
+    Notified(Option<u32>),
     Errored(String),
     InitProfile(UnboundedSender<PowerProfileRequest>, Power),
     Profile(Power),
     SelectProfile(Power),
+    ToggleRules,
+    SetAcProfile(usize),
+    SetBatteryProfile(usize),
+    SetPowerSaverLevel(u8),
     Frame(Instant),
     Token(TokenUpdate),
     OpenSettings,
@@ -204,16 +950,44 @@ impl cosmic::Application for CosmicBatteryApplet {
             cosmic::iced::Command::perform(get_charging_limit(), |limit| {
                 cosmic::app::Message::App(Message::InitChargingLimit(limit))
             });
+        let init_charge_profiles_cmd =
+            cosmic::iced::Command::perform(get_charge_profiles(), |profiles| {
+                cosmic::app::Message::App(Message::InitChargeProfiles(profiles))
+            });
+        let init_custom_thresholds_cmd =
+            cosmic::iced::Command::perform(custom_charge_thresholds(), |supported| {
+                cosmic::app::Message::App(Message::InitCustomThresholds(supported))
+            });
+        let init_graphics_cmd = cosmic::iced::Command::perform(get_graphics(), |graphics| {
+            cosmic::app::Message::App(Message::InitGraphics(graphics))
+        });
         (
             Self {
                 core,
                 icon_name: "battery-symbolic".to_string(),
+                rule_options: vec![
+                    fl!("rule-unchanged"),
+                    fl!("battery"),
+                    fl!("balanced"),
+                    fl!("performance"),
+                ],
+                graphics_options: Graphics::ALL
+                    .iter()
+                    .map(|graphics| graphics_name(*graphics))
+                    .collect(),
                 display_icon_name: "display-brightness-symbolic".to_string(),
+                boot_id: boot_id(),
                 token_tx: None,
 
                 ..Default::default()
             },
-            Command::batch(vec![zbus_session_cmd, init_charging_limit_cmd]),
+            Command::batch(vec![
+                zbus_session_cmd,
+                init_charging_limit_cmd,
+                init_charge_profiles_cmd,
+                init_custom_thresholds_cmd,
+                init_graphics_cmd,
+            ]),
         )
     }
 
@@ -244,19 +1018,52 @@ impl cosmic::Application for CosmicBatteryApplet {
                     let _ = tx.send(settings_daemon::Request::SetDisplayBrightness(brightness));
                 }
             }
-            Message::InitChargingLimit(enable) => {
-                self.set_charging_limit(enable);
+            Message::InitChargingLimit(limit) => {
+                self.set_charging_limit(limit);
+                if let Some(thresholds) = limit
+                    .map(clamp_thresholds)
+                    .filter(|t| *t != self.config.charge_thresholds)
+                {
+                    self.config.charge_thresholds = thresholds;
+                    self.write_config();
+                }
+            }
+            Message::InitChargeProfiles(mut profiles) => {
+                for profile in &mut profiles {
+                    profile.thresholds = clamp_thresholds(profile.thresholds);
+                }
+                self.charge_profiles = profiles;
+            }
+            Message::InitCustomThresholds(supported) => {
+                self.custom_thresholds = supported;
             }
             Message::SetChargingLimit(chain, enable) => {
                 self.timeline.set_chain(chain).start();
-                self.set_charging_limit(enable);
-
-                if enable {
-                    return cosmic::iced::Command::perform(set_charging_limit(), |_| {
-                        cosmic::app::Message::None
-                    });
+                self.custom_charge = false;
+                self.set_charging_limit(enable.then_some(self.config.charge_thresholds));
+                return self.apply_charging_limit();
+            }
+            Message::SelectChargeProfile(thresholds) => {
+                self.custom_charge = false;
+                self.set_charging_limit(Some(thresholds));
+                return self.apply_charging_limit();
+            }
+            Message::CustomChargeLimit => {
+                self.custom_charge = true;
+            }
+            Message::SetChargeStart(start) => {
+                if let Some((_, end)) = self.charging_limit {
+                    self.set_charging_limit(Some((start.min(end.saturating_sub(1)), end)));
                 }
             }
+            Message::SetChargeEnd(end) => {
+                if let Some((start, _)) = self.charging_limit {
+                    self.set_charging_limit(Some((start.min(end.saturating_sub(1)), end)));
+                }
+            }
+            Message::ApplyChargingLimit => {
+                return self.apply_charging_limit();
+            }
             Message::Errored(why) => {
                 tracing::error!("{}", why);
             }
@@ -268,6 +1075,8 @@ impl cosmic::Application for CosmicBatteryApplet {
                         let _ = tx.send(KeyboardBacklightRequest::Get);
                     }
                     self.timeline = Timeline::new();
+                    self.confirm_restart = false;
+                    self.confirm_release = false;
 
                     let new_id = window::Id::unique();
                     self.popup.replace(new_id);
@@ -287,7 +1096,11 @@ impl cosmic::Application for CosmicBatteryApplet {
                     if let Some(tx) = self.power_profile_sender.as_ref() {
                         let _ = tx.send(PowerProfileRequest::Get);
                     }
-                    return get_popup(popup_settings);
+                    return Command::batch(vec![
+                        get_popup(popup_settings),
+                        self.refresh_history(),
+                        self.refresh_profile_status(),
+                    ]);
                 }
             }
             Message::UpowerDevice(event) => match event {
@@ -297,12 +1110,51 @@ impl cosmic::Application for CosmicBatteryApplet {
                     time_to_empty,
                 } => {
                     self.update_battery(percent, on_battery);
-                    self.time_remaining = Duration::from_secs(time_to_empty as u64);
+                    self.apply_profile_rules();
+                    // the power draw is only known to the batteries themselves
+                    let time_to_empty = Duration::from_secs(time_to_empty as u64);
+                    let sample_cmd = cosmic::iced::Command::perform(get_energy(), move |energy| {
+                        cosmic::app::Message::App(Message::BatterySample(
+                            percent,
+                            !on_battery,
+                            time_to_empty,
+                            energy,
+                        ))
+                    });
+                    return Command::batch(vec![self.update_alert(), sample_cmd]);
                 }
                 DeviceDbusEvent::NoBattery => {
                     std::process::exit(0);
                 }
             },
+            Message::BatterySample(percent, charging, time_to_empty, energy) => {
+                self.estimator.push(
+                    std::time::Instant::now(),
+                    percent,
+                    energy,
+                    charging,
+                    time_to_empty,
+                );
+            }
+            Message::Wayland(update) => match update {
+                WaylandUpdate::Init(tx) => {
+                    self.wayland_tx = Some(tx);
+                }
+                WaylandUpdate::Finished => {
+                    self.wayland_tx = None;
+                    self.toplevels.clear();
+                }
+                WaylandUpdate::Toplevel(update) => match update {
+                    ToplevelUpdate::Update(handle, info) => {
+                        if let Some(toplevel) = self.toplevels.iter_mut().find(|t| t.0 == handle) {
+                            toplevel.1 = info;
+                        } else {
+                            self.toplevels.push((handle, info));
+                        }
+                    }
+                    ToplevelUpdate::Remove(handle) => self.toplevels.retain(|t| t.0 != handle),
+                },
+            },
             Message::KeyboardBacklight(event) => match event {
                 KeyboardBacklightUpdate::Sender(tx) => {
                     self.kbd_sender = Some(tx);
@@ -317,17 +1169,41 @@ impl cosmic::Application for CosmicBatteryApplet {
             Message::InitProfile(tx, profile) => {
                 self.power_profile_sender.replace(tx);
                 self.power_profile = profile;
+                self.apply_profile_rules();
             }
             Message::Profile(profile) => {
                 self.power_profile = profile;
                 if let Some(tx) = &self.kbd_sender {
                     let _ = tx.send(KeyboardBacklightRequest::Get);
                 }
+                if self.popup.is_some() {
+                    return self.refresh_profile_status();
+                }
             }
             Message::SelectProfile(profile) => {
                 if let Some(tx) = self.power_profile_sender.as_ref() {
                     let _ = tx.send(PowerProfileRequest::Set(profile));
                 }
+                if self.active_rule.is_some() {
+                    self.rule_overridden = true;
+                }
+            }
+            Message::ToggleRules => {
+                self.show_rules = !self.show_rules;
+            }
+            Message::SetAcProfile(i) => {
+                self.config.ac_profile = RULE_PROFILES.get(i).copied().flatten();
+                self.write_config();
+                self.apply_profile_rules();
+            }
+            Message::SetBatteryProfile(i) => {
+                self.config.battery_profile = RULE_PROFILES.get(i).copied().flatten();
+                self.write_config();
+                self.apply_profile_rules();
+            }
+            Message::SetPowerSaverLevel(level) => {
+                self.config.power_saver_level = level;
+                self.apply_profile_rules();
             }
             Message::CloseRequested(id) => {
                 if Some(id) == self.popup {
@@ -352,7 +1228,17 @@ impl cosmic::Application for CosmicBatteryApplet {
                 TokenUpdate::Finished => {
                     self.token_tx = None;
                 }
-                TokenUpdate::ActivationToken { token, .. } => {
+                TokenUpdate::ActivationToken { token, exec, .. } => {
+                    if let Some(mut envs) = self.pending_launches.remove(&exec) {
+                        if let Some(token) = token {
+                            envs.push(("XDG_ACTIVATION_TOKEN".to_string(), token.clone()));
+                            envs.push(("DESKTOP_STARTUP_ID".to_string(), token));
+                        }
+                        tokio::spawn(async move {
+                            cosmic::desktop::spawn_desktop_exec(exec, envs, None).await
+                        });
+                        return Command::none();
+                    }
                     let mut cmd = std::process::Command::new("cosmic-settings");
                     cmd.arg("power");
                     if let Some(token) = token {
@@ -363,11 +1249,14 @@ impl cosmic::Application for CosmicBatteryApplet {
                 }
             },
             Message::GpuOn(path, name, app_list) => {
-                let toggled = self
+                let (toggled, was_busy) = self
                     .gpus
                     .get(&path)
-                    .map(|data| data.toggled)
+                    .map(|data| (data.toggled, data.app_list.is_some()))
                     .unwrap_or_default();
+                // the last application using the GPU quit, and nothing else
+                // keeps it awake, not even a display
+                let idle = was_busy && app_list.is_none();
                 self.gpus.insert(
                     path,
                     GPUData {
@@ -376,15 +1265,213 @@ impl cosmic::Application for CosmicBatteryApplet {
                         toggled,
                     },
                 );
+                if idle && self.config.dgpu_power_off {
+                    return cosmic::iced::Command::perform(power_off_graphics(), |res| match res {
+                        Ok(()) => cosmic::app::Message::None,
+                        Err(err) => cosmic::app::Message::App(Message::Errored(format!(
+                            "Failed to power off discrete GPU: {err}"
+                        ))),
+                    });
+                }
             }
             Message::GpuOff(path) => {
                 self.gpus.remove(&path);
             }
+            Message::QuitGpuApp(app) => {
+                if let Err(err) = self.quit_gpu_app(&app) {
+                    tracing::error!("Failed to quit process {:?}: {}", app.pid, err);
+                }
+            }
+            Message::RelaunchGpuApp(app) => {
+                let (Some(pid), Some(exec)) = (app.pid, app.exec.clone()) else {
+                    return Command::none();
+                };
+                let app_id = match self.quit_gpu_app(&app) {
+                    Ok(app_id) => app_id,
+                    Err(err) => {
+                        tracing::error!("Failed to quit process {}: {}", pid, err);
+                        return Command::none();
+                    }
+                };
+                return cosmic::iced::Command::perform(
+                    async move {
+                        // a new instance could hand over to the old one while it runs
+                        if !wait_for_exit(pid, Duration::from_secs(10)).await {
+                            return None;
+                        }
+                        // the desktop entry starts the application as usual,
+                        // rather than with the arguments of the old instance
+                        let exec = app_id.as_deref().and_then(desktop_exec).unwrap_or(exec);
+                        Some((exec, integrated_gpu_env().await))
+                    },
+                    move |launch| match launch {
+                        Some((exec, envs)) => {
+                            cosmic::app::Message::App(Message::LaunchOnIntegrated(exec, envs))
+                        }
+                        None => cosmic::app::Message::App(Message::Errored(format!(
+                            "Process {pid} did not quit, not launching it again"
+                        ))),
+                    },
+                );
+            }
+            Message::LaunchOnIntegrated(exec, envs) => {
+                // launched the way the app list does, once a token arrives
+                if let Some(tx) = self.token_tx.as_ref() {
+                    self.pending_launches.insert(exec.clone(), envs);
+                    let _ = tx.send(TokenRequest {
+                        app_id: Self::APP_ID.to_string(),
+                        exec,
+                    });
+                } else {
+                    tracing::error!("Wayland tx is None");
+                }
+            }
             Message::ToggleGpuApps(path) => {
                 if let Some(data) = self.gpus.get_mut(&path) {
                     data.toggled = !data.toggled;
                 }
             }
+            Message::SelectHistory(kind) => {
+                self.history_kind = kind;
+                self.history.clear();
+                return self.refresh_history();
+            }
+            Message::Refresh => {
+                let mut commands = vec![self.refresh_history(), self.refresh_profile_status()];
+                if self.show_details {
+                    commands.push(self.refresh_details());
+                }
+                return Command::batch(commands);
+            }
+            Message::ToggleDetails => {
+                self.show_details = !self.show_details;
+                if self.show_details {
+                    return self.refresh_details();
+                }
+            }
+            Message::Details(details) => {
+                self.details = details;
+            }
+            Message::ProfileStatus(status) => {
+                self.profile_status = status;
+            }
+            Message::ConfirmRelease(confirm) => {
+                self.confirm_release = confirm;
+            }
+            Message::ReleaseHolds => {
+                self.confirm_release = false;
+                // keep the profile in use, only dropping the holds on it
+                let profile = self.power_profile;
+                return cosmic::iced::Command::perform(
+                    async move {
+                        if let Err(err) = release_profile_holds(profile).await {
+                            tracing::error!("Failed to release profile holds: {}", err);
+                        }
+                        profile
+                    },
+                    |profile| cosmic::app::Message::App(Message::Profile(profile)),
+                );
+            }
+            Message::History(kind, history) => {
+                // drop a reply for the view that was left in the meantime
+                if kind == self.history_kind {
+                    self.history = history;
+                }
+            }
+            Message::Peripherals(peripherals) => {
+                self.peripherals = peripherals;
+            }
+            Message::SetPeripheralWarning(id, warn) => {
+                self.config.peripheral_warnings.retain(|i| i != &id);
+                if warn {
+                    self.config.peripheral_warnings.push(id);
+                }
+                self.write_config();
+            }
+            Message::ConfigChanged(mut config) => {
+                config.charge_thresholds = clamp_thresholds(config.charge_thresholds);
+                self.config = config;
+                self.apply_profile_rules();
+                return self.update_alert();
+            }
+            Message::ToggleNotificationSettings => {
+                self.notification_settings = !self.notification_settings;
+            }
+            Message::SetWarningLevel(level) => {
+                self.config.warning_level = level;
+                if level > 0 {
+                    self.config.critical_level = self.config.critical_level.min(level);
+                }
+            }
+            Message::SetCriticalLevel(level) => {
+                self.config.critical_level = level;
+                if self.config.warning_level > 0 {
+                    self.config.warning_level = self.config.warning_level.max(level);
+                }
+            }
+            Message::SetNotifyFull(chain, enable) => {
+                self.timeline.set_chain(chain).start();
+                self.config.notify_full = enable;
+                self.write_config();
+            }
+            Message::SaveConfig => {
+                self.write_config();
+            }
+            Message::InitGraphics(graphics) => {
+                self.graphics = graphics;
+                self.session_graphics = graphics;
+            }
+            Message::Graphics(graphics) => {
+                self.graphics = graphics;
+            }
+            Message::SelectGraphics(i) => {
+                let Some(graphics) = Graphics::ALL.get(i).copied() else {
+                    return Command::none();
+                };
+                self.graphics = Some(graphics);
+                // remember the running mode, until the next restart applies
+                // the selected one
+                if let (Some(boot), Some(session)) = (&self.boot_id, self.running_graphics()) {
+                    if self
+                        .config
+                        .graphics_session
+                        .as_ref()
+                        .map_or(true, |(b, _)| b != boot)
+                    {
+                        self.config.graphics_session = Some((boot.clone(), session));
+                        self.write_config();
+                    }
+                }
+                return cosmic::iced::Command::perform(
+                    async move {
+                        if let Err(err) = set_graphics(graphics).await {
+                            tracing::error!("Failed to set graphics mode: {}", err);
+                        }
+                        get_graphics().await
+                    },
+                    |graphics| cosmic::app::Message::App(Message::Graphics(graphics)),
+                );
+            }
+            Message::ConfirmRestart(confirm) => {
+                self.confirm_restart = confirm;
+            }
+            Message::Restart => {
+                self.confirm_restart = false;
+                return cosmic::iced::Command::perform(restart(), |res| match res {
+                    Ok(()) => cosmic::app::Message::None,
+                    Err(err) => cosmic::app::Message::App(Message::Errored(format!(
+                        "Failed to restart: {err}"
+                    ))),
+                });
+            }
+            Message::SetDgpuPowerOff(chain, enable) => {
+                self.timeline.set_chain(chain).start();
+                self.config.dgpu_power_off = enable;
+                self.write_config();
+            }
+            Message::Notified(id) => {
+                self.notification_id = id.unwrap_or_default();
+            }
             Message::ZbusConnection(Err(err)) => {
                 tracing::error!("Failed to connect to session dbus: {}", err);
             }
@@ -447,15 +1534,19 @@ impl cosmic::Application for CosmicBatteryApplet {
 
     fn view_window(&self, _id: window::Id) -> Element<Message> {
         let name = text::body(fl!("battery"));
-        let description = text::caption(if !self.on_battery {
-            format!("{:.0}%", self.battery_percent)
-        } else {
-            format!(
+        let full = self.full_percent();
+        let description = text::caption(match self.estimator.remaining(full) {
+            Some(remaining) => format!(
                 "{} {} ({:.0}%)",
-                format_duration(self.time_remaining),
-                fl!("until-empty"),
+                format_duration(remaining),
+                if self.on_battery {
+                    fl!("until-empty")
+                } else {
+                    fl!("until-full")
+                },
                 self.battery_percent
-            )
+            ),
+            None => format!("{:.0}%", self.battery_percent),
         });
 
         let mut content = vec![
@@ -468,6 +1559,8 @@ impl cosmic::Application for CosmicBatteryApplet {
                 .align_items(Alignment::Center),
             )
             .into(),
+            padded_control(history_view(self.history_kind, &self.history)).into(),
+            details_view(self.details.as_ref(), self.show_details),
             padded_control(divider::horizontal::default()).into(),
             menu_button(
                 row![
@@ -532,22 +1625,55 @@ impl cosmic::Application for CosmicBatteryApplet {
             )
             .on_press(Message::SelectProfile(Power::Performance))
             .into(),
+        ];
+        if let Some(status) = self.profile_status.as_ref() {
+            content.extend(self.profile_status_view(status));
+        }
+        content.push(revealer_header(
+            fl!("automatic-profiles"),
+            self.show_rules,
+            Message::ToggleRules,
+        ));
+        if self.show_rules {
+            content.push(self.rules_view());
+        }
+        content.extend([
             padded_control(divider::horizontal::default()).into(),
             padded_control(
                 anim!(
                     //toggler
                     MAX_CHARGE,
                     &self.timeline,
-                    fl!("max-charge"),
-                    self.charging_limit,
+                    fl!(
+                        "max-charge",
+                        percent = self
+                            .charging_limit
+                            .unwrap_or(self.config.charge_thresholds)
+                            .1
+                    ),
+                    self.charging_limit.is_some(),
                     Message::SetChargingLimit,
                 )
                 .text_size(14)
                 .width(Length::Fill),
             )
             .into(),
-            padded_control(divider::horizontal::default()).into(),
-        ];
+        ]);
+
+        if let Some(thresholds) = self.charging_limit {
+            content.push(self.charge_profile_view(thresholds));
+        }
+        content.push(padded_control(divider::horizontal::default()).into());
+
+        content.push(revealer_header(
+            fl!("notifications"),
+            self.notification_settings,
+            Message::ToggleNotificationSettings,
+        ));
+        if self.notification_settings {
+            content.push(notifications::settings_view(&self.config, &self.timeline));
+        }
+        content.push(padded_control(divider::horizontal::default()).into());
 
         if let Some(max_screen_brightness) = self.max_screen_brightness {
             if let Some(screen_brightness) = self.screen_brightness {
@@ -607,6 +1733,19 @@ impl cosmic::Application for CosmicBatteryApplet {
 
         content.push(padded_control(divider::horizontal::default()).into());
 
+        if !self.peripherals.is_empty() {
+            content.push(padded_control(text::heading(fl!("devices"))).into());
+            for peripheral in &self.peripherals {
+                content.push(self.peripheral_view(peripheral));
+            }
+            content.push(padded_control(divider::horizontal::default()).into());
+        }
+
+        if let Some(graphics) = self.graphics {
+            content.push(self.graphics_view(graphics));
+            content.push(padded_control(divider::horizontal::default()).into());
+        }
+
         if !self.gpus.is_empty() {
             content.push(
                 padded_control(
@@ -645,34 +1784,14 @@ impl cosmic::Application for CosmicBatteryApplet {
                 continue;
             }
 
-            content.push(
-                menu_button(
-                    row![
-                        text::body(fl!(
-                            "dgpu-applications",
-                            gpu_name = format!("\"{}\"", gpu.name.trim())
-                        ))
-                        .width(Length::Fill)
-                        .vertical_alignment(Vertical::Center),
-                        container(
-                            icon::from_name(if gpu.toggled {
-                                "go-down-symbolic"
-                            } else {
-                                "go-up-symbolic"
-                            })
-                            .size(14)
-                            .symbolic(true)
-                        )
-                        .align_x(Horizontal::Center)
-                        .align_y(Vertical::Center)
-                        .width(Length::Fixed(24.0))
-                        .height(Length::Fixed(24.0)),
-                    ]
-                    .align_items(Alignment::Center),
-                )
-                .on_press(Message::ToggleGpuApps(key.clone()))
-                .into(),
-            );
+            content.push(revealer_header(
+                fl!(
+                    "dgpu-applications",
+                    gpu_name = format!("\"{}\"", gpu.name.trim())
+                ),
+                gpu.toggled,
+                Message::ToggleGpuApps(key.clone()),
+            ));
 
             if gpu.toggled
                 && !self.core.applet.configure.as_ref().is_some_and(|c| {
@@ -693,6 +1812,7 @@ impl cosmic::Application for CosmicBatteryApplet {
                                 },
                                 column![text::body(&app.name), text::caption(&app.secondary)]
                                     .width(Length::Fill),
+                                gpu_app_actions(app),
                             ]
                             .spacing(8)
                             .align_items(Alignment::Center),
@@ -738,7 +1858,19 @@ impl cosmic::Application for CosmicBatteryApplet {
                 .as_subscription()
                 .map(|(_, now)| Message::Frame(now)),
             activation_token_subscription(0).map(Message::Token),
+            wayland_subscription().map(Message::Wayland),
+            peripheral_subscription(0).map(Message::Peripherals),
+            self.core.watch_config(config::APP_ID).map(|u| {
+                for err in u.errors {
+                    tracing::error!(?err, "Error watching config");
+                }
+                Message::ConfigChanged(u.config)
+            }),
         ];
+        if self.popup.is_some() {
+            subscriptions
+                .push(cosmic::iced::time::every(Duration::from_secs(60)).map(|_| Message::Refresh));
+        }
         if let Some(conn) = self.zbus_connection.clone() {
             subscriptions.push(settings_daemon::subscription(conn).map(Message::SettingsDaemon));
         }
@@ -0,0 +1,139 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Charge thresholds of batteries controlled by the kernel, for laptops
+//! without the System76 power daemon.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use zbus::Connection;
+
+use super::{upower::DeviceProxy, ChargeThresholds, NO_CHARGE_THRESHOLDS};
+
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+const START_THRESHOLD: &str = "charge_control_start_threshold";
+const END_THRESHOLD: &str = "charge_control_end_threshold";
+
+#[derive(Debug)]
+struct Battery<'a> {
+    path: PathBuf,
+    // UPower can toggle the thresholds it is configured with when the
+    // attributes are only writable by root, asking through polkit
+    device: Option<DeviceProxy<'a>>,
+}
+
+/// The batteries of which the kernel exposes charge thresholds.
+#[derive(Debug)]
+pub struct ChargeControl<'a> {
+    batteries: Vec<Battery<'a>>,
+}
+
+impl<'a> ChargeControl<'a> {
+    /// Returns `None` when no battery supports charge thresholds.
+    pub async fn new(conn: &'a Connection) -> Option<ChargeControl<'a>> {
+        let mut batteries = Vec::new();
+        for entry in fs::read_dir(POWER_SUPPLY).ok()?.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = entry.path();
+            if !name.starts_with("BAT") || !path.join(END_THRESHOLD).exists() {
+                continue;
+            }
+
+            let device = match DeviceProxy::builder(conn)
+                .path(format!("/org/freedesktop/UPower/devices/battery_{name}"))
+            {
+                Ok(builder) => builder.build().await.ok(),
+                Err(_) => None,
+            };
+            batteries.push(Battery { path, device });
+        }
+        batteries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        (!batteries.is_empty()).then_some(Self { batteries })
+    }
+
+    /// Thresholds of the first battery, which are the same for all of them
+    /// once set from here.
+    pub fn thresholds(&self) -> io::Result<ChargeThresholds> {
+        let path = &self.batteries[0].path;
+        // some drivers only support stopping at a threshold
+        let start = read_threshold(&path.join(START_THRESHOLD)).unwrap_or(0);
+        let end = read_threshold(&path.join(END_THRESHOLD))?;
+        Ok((start, end))
+    }
+
+    /// Whether thresholds can be written directly, rather than only toggled
+    /// through UPower with the values it is configured with.
+    pub fn thresholds_writable(&self) -> bool {
+        self.batteries.iter().all(|battery| {
+            fs::OpenOptions::new()
+                .write(true)
+                .open(battery.path.join(END_THRESHOLD))
+                .is_ok()
+        })
+    }
+
+    /// Set the thresholds of every battery, or let them charge fully when
+    /// `None`.
+    pub async fn set_thresholds(&self, thresholds: Option<ChargeThresholds>) {
+        for battery in &self.batteries {
+            let res = write_thresholds(&battery.path, thresholds.unwrap_or(NO_CHARGE_THRESHOLDS));
+            let Err(err) = res else {
+                continue;
+            };
+
+            match &battery.device {
+                Some(device)
+                    if err.kind() == io::ErrorKind::PermissionDenied
+                        && device.charge_threshold_supported().await.unwrap_or(false) =>
+                {
+                    tracing::warn!(
+                        "Cannot write charge thresholds {:?} of {:?}, using those configured in UPower",
+                        thresholds,
+                        battery.path
+                    );
+                    if let Err(err) = device.enable_charge_threshold(thresholds.is_some()).await {
+                        tracing::error!("Failed to set charge threshold through UPower: {}", err);
+                    }
+                }
+                _ => {
+                    tracing::error!(
+                        "Failed to set charge thresholds of {:?}: {}",
+                        battery.path,
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn read_threshold(path: &Path) -> io::Result<u8> {
+    fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_thresholds(path: &Path, (start, end): ChargeThresholds) -> io::Result<()> {
+    let start_path = path.join(START_THRESHOLD);
+    let end_path = path.join(END_THRESHOLD);
+    let has_start = start_path.exists();
+
+    // the kernel rejects a start threshold above the current end threshold,
+    // so raise the end first when needed
+    let end_first = read_threshold(&end_path).map_or(true, |current| start >= current);
+    if end_first {
+        fs::write(&end_path, end.to_string())?;
+    }
+    if has_start {
+        fs::write(&start_path, start.to_string())?;
+    }
+    if !end_first {
+        fs::write(&end_path, end.to_string())?;
+    }
+    Ok(())
+}
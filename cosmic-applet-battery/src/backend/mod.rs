@@ -2,16 +2,34 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use cosmic::iced::{self, futures::SinkExt, subscription};
-use std::{fmt::Debug, hash::Hash};
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-use zbus::{Connection, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use tokio::sync::{
+    mpsc::{UnboundedReceiver, UnboundedSender},
+    OnceCell,
+};
+use zbus::{zvariant::OwnedValue, Connection, Result};
 
-use self::{power_daemon::PowerDaemonProxy, power_profiles::PowerProfilesProxy};
+use self::{
+    charge_control::ChargeControl,
+    power_daemon::PowerDaemonProxy,
+    power_profiles::PowerProfilesProxy,
+    upower::{DeviceProxy, UPowerProxy},
+};
 
+mod charge_control;
 mod power_daemon;
 mod power_profiles;
+pub mod upower;
 
-#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+static SYSTEM: OnceCell<Connection> = OnceCell::const_new();
+
+// system bus connection shared by the requests made every few minutes
+pub async fn system_connection() -> Result<&'static Connection> {
+    SYSTEM.get_or_try_init(Connection::system).await
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub enum Power {
     Battery,
     #[default]
@@ -208,42 +226,318 @@ pub enum PowerProfileUpdate {
     Error(String),
 }
 
-// check if battery charging thresholds is set
-pub async fn get_charging_limit() -> bool {
-    if let Ok(conn) = Connection::system().await {
-        if let Ok(backend) = get_power_backend(&conn, &BackendType::S76PowerDaemon).await {
-            match backend {
-                Backend::S76PowerDaemon(proxy) => {
-                    if let Ok((start, end)) = proxy.get_charge_thresholds().await {
-                        return start > 0 || end > 0;
-                    }
-                }
-                Backend::PowerProfilesDaemon(_) => {
-                    tracing::info!("Power Profiles Daemon is not supported.");
+/// A profile requested by an application through power-profiles-daemon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileHold {
+    pub application_id: String,
+    pub profile: Power,
+    pub reason: String,
+}
 
-                    return false;
-                }
-            };
+impl ProfileHold {
+    fn from_dict(dict: &HashMap<String, OwnedValue>) -> Option<Self> {
+        let string = |key: &str| {
+            dict.get(key)
+                .and_then(|value| <&str>::try_from(value).ok())
+                .map(str::to_string)
+        };
+        Some(Self {
+            application_id: string("ApplicationId").unwrap_or_default(),
+            profile: match string("Profile")?.as_str() {
+                "power-saver" => Power::Battery,
+                "balanced" => Power::Balanced,
+                "performance" => Power::Performance,
+                _ => return None,
+            },
+            reason: string("Reason").unwrap_or_default(),
+        })
+    }
+}
+
+/// Why power-profiles-daemon doesn't apply the profile as requested.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct ProfileStatus {
+    pub holds: Vec<ProfileHold>,
+    /// Reasons the performance profile runs degraded, such as `lap-detected`.
+    pub degraded: Vec<String>,
+    /// Why the performance profile is unavailable, if it is.
+    pub inhibited: String,
+}
+
+// get the holds and performance reasons of power-profiles-daemon, if it is running
+pub async fn get_profile_status() -> Option<ProfileStatus> {
+    let conn = system_connection().await.ok()?;
+    let ppd = PowerProfilesProxy::new(conn).await.ok()?;
+    let holds = match ppd.active_profile_holds().await {
+        Ok(holds) => holds.iter().filter_map(ProfileHold::from_dict).collect(),
+        Err(err) => {
+            tracing::debug!("Failed to get profile holds: {}", err);
+            return None;
+        }
+    };
+    let degraded = ppd.performance_degraded().await.unwrap_or_default();
+    Some(ProfileStatus {
+        holds,
+        degraded: degraded
+            .split(',')
+            .filter(|reason| !reason.is_empty())
+            .map(str::to_string)
+            .collect(),
+        inhibited: ppd.performance_inhibited().await.unwrap_or_default(),
+    })
+}
+
+// release all profile holds by picking `profile`. Their cookies are only known
+// to the applications that took them, as the applet takes none of its own, but
+// power-profiles-daemon drops every hold once the user picks a profile.
+pub async fn release_profile_holds(profile: Power) -> Result<()> {
+    let conn = system_connection().await?;
+    let ppd = PowerProfilesProxy::new(conn).await?;
+    set_power_profile(Backend::PowerProfilesDaemon(ppd), profile).await
+}
+
+/// Battery percentages at which charging starts and stops.
+pub type ChargeThresholds = (u8, u8);
+
+/// Thresholds used when the limit is enabled without a matching profile.
+pub const DEFAULT_CHARGE_THRESHOLDS: ChargeThresholds = (70, 80);
+
+/// Thresholds that let the battery charge fully.
+pub const NO_CHARGE_THRESHOLDS: ChargeThresholds = (0, 100);
+
+/// A set of thresholds offered by the power daemon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChargeProfile {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub thresholds: ChargeThresholds,
+}
+
+impl ChargeProfile {
+    fn from_dict(dict: &HashMap<String, OwnedValue>) -> Option<Self> {
+        let string = |key: &str| {
+            dict.get(key)
+                .and_then(|value| <&str>::try_from(value).ok())
+                .map(str::to_string)
+        };
+        let percent = |key: &str| dict.get(key).and_then(|value| u8::try_from(value).ok());
+        Some(Self {
+            id: string("id")?,
+            title: string("title")?,
+            description: string("description").unwrap_or_default(),
+            thresholds: (percent("start")?, percent("end")?),
+        })
+    }
+
+    /// Whether charging stops before the battery is full.
+    pub fn is_limited(&self) -> bool {
+        self.thresholds.1 < 100
+    }
+}
+
+/// What controls the charge thresholds of the battery.
+#[derive(Debug)]
+enum ChargeBackend<'a> {
+    S76PowerDaemon(PowerDaemonProxy<'a>),
+    Kernel(ChargeControl<'a>),
+}
+
+// prefer s76 power_daemon, then fall back to the thresholds exposed by the kernel
+async fn get_charge_backend(conn: &Connection) -> Option<ChargeBackend<'_>> {
+    if let Ok(Backend::S76PowerDaemon(proxy)) =
+        get_power_backend(conn, &BackendType::S76PowerDaemon).await
+    {
+        // the proxy is created whether or not the daemon is running
+        if proxy.get_charge_thresholds().await.is_ok() {
+            return Some(ChargeBackend::S76PowerDaemon(proxy));
         }
     }
-    false
+    ChargeControl::new(conn).await.map(ChargeBackend::Kernel)
+}
+
+// get the battery charging thresholds, if charging stops before the battery is full
+pub async fn get_charging_limit() -> Option<ChargeThresholds> {
+    let conn = Connection::system().await.ok()?;
+    let (start, end) = match get_charge_backend(&conn).await? {
+        ChargeBackend::S76PowerDaemon(proxy) => proxy.get_charge_thresholds().await.ok()?,
+        ChargeBackend::Kernel(control) => match control.thresholds() {
+            Ok(thresholds) => thresholds,
+            Err(err) => {
+                tracing::error!("Failed to read charge thresholds: {}", err);
+                return None;
+            }
+        },
+    };
+    (end > 0 && end < 100).then_some((start, end))
 }
 
-// set battery charging thresholds via s76 power_daemon
-pub async fn set_charging_limit() -> Result<()> {
+// get the charging threshold profiles offered by s76 power_daemon
+pub async fn get_charge_profiles() -> Vec<ChargeProfile> {
     if let Ok(conn) = Connection::system().await {
-        if let Ok(backend) = get_power_backend(&conn, &BackendType::S76PowerDaemon).await {
-            match backend {
-                Backend::S76PowerDaemon(proxy) => {
-                    let _ = proxy.set_charge_thresholds(&(70, 80)).await;
+        if let Some(ChargeBackend::S76PowerDaemon(proxy)) = get_charge_backend(&conn).await {
+            match proxy.get_charge_profiles().await {
+                Ok(profiles) => {
+                    return profiles
+                        .iter()
+                        .filter_map(ChargeProfile::from_dict)
+                        .collect();
                 }
-                Backend::PowerProfilesDaemon(_) => {
-                    tracing::info!(
-                        "Setting charging limit via Power Profiles Daemon is not supported."
-                    );
+                Err(err) => {
+                    tracing::error!("Failed to get charge profiles: {}", err);
                 }
-            };
+            }
+        }
+    }
+    Vec::new()
+}
+
+// whether arbitrary thresholds can be set, not only the profiles or the
+// thresholds UPower is configured with
+pub async fn custom_charge_thresholds() -> bool {
+    let Ok(conn) = Connection::system().await else {
+        return false;
+    };
+    match get_charge_backend(&conn).await {
+        Some(ChargeBackend::S76PowerDaemon(_)) => true,
+        Some(ChargeBackend::Kernel(control)) => control.thresholds_writable(),
+        None => false,
+    }
+}
+
+// set battery charging thresholds, or let the battery charge fully when `None`
+pub async fn set_charging_limit(thresholds: Option<ChargeThresholds>) -> Result<()> {
+    let conn = Connection::system().await?;
+    match get_charge_backend(&conn).await {
+        Some(ChargeBackend::S76PowerDaemon(proxy)) => {
+            proxy
+                .set_charge_thresholds(&thresholds.unwrap_or(NO_CHARGE_THRESHOLDS))
+                .await?;
+        }
+        Some(ChargeBackend::Kernel(control)) => control.set_thresholds(thresholds).await,
+        None => tracing::info!("Setting charging limit is not supported."),
+    }
+    Ok(())
+}
+
+/// Graphics mode of s76 power_daemon on switchable systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Graphics {
+    Integrated,
+    Hybrid,
+    Nvidia,
+    Compute,
+}
+
+impl Graphics {
+    pub const ALL: [Self; 4] = [Self::Integrated, Self::Hybrid, Self::Nvidia, Self::Compute];
+
+    fn from_vendor(vendor: &str) -> Option<Self> {
+        match vendor {
+            "integrated" => Some(Self::Integrated),
+            "hybrid" => Some(Self::Hybrid),
+            "nvidia" => Some(Self::Nvidia),
+            "compute" => Some(Self::Compute),
+            _ => None,
+        }
+    }
+
+    fn vendor(self) -> &'static str {
+        match self {
+            Self::Integrated => "integrated",
+            Self::Hybrid => "hybrid",
+            Self::Nvidia => "nvidia",
+            Self::Compute => "compute",
+        }
+    }
+}
+
+async fn switchable_graphics(conn: &Connection) -> Option<PowerDaemonProxy<'_>> {
+    let proxy = PowerDaemonProxy::new(conn).await.ok()?;
+    proxy.get_switchable().await.ok()?.then_some(proxy)
+}
+
+// identifies the current boot, to tell whether the computer restarted since
+pub fn boot_id() -> Option<String> {
+    std::fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .map(|id| id.trim().to_string())
+}
+
+// get the graphics mode that applies after the next restart, if graphics are switchable
+pub async fn get_graphics() -> Option<Graphics> {
+    let conn = Connection::system().await.ok()?;
+    let proxy = switchable_graphics(&conn).await?;
+    match proxy.get_graphics().await {
+        Ok(vendor) => Graphics::from_vendor(&vendor),
+        Err(err) => {
+            tracing::error!("Failed to get graphics mode: {}", err);
+            None
         }
     }
+}
+
+pub async fn set_graphics(graphics: Graphics) -> Result<()> {
+    let conn = Connection::system().await?;
+    PowerDaemonProxy::new(&conn)
+        .await?
+        .set_graphics(graphics.vendor())
+        .await
+}
+
+// power off the discrete GPU, unless it already is
+pub async fn power_off_graphics() -> Result<()> {
+    let conn = system_connection().await?;
+    let Some(proxy) = switchable_graphics(conn).await else {
+        return Ok(());
+    };
+    if proxy.get_graphics_power().await? {
+        proxy.set_graphics_power(false).await?;
+    }
     Ok(())
 }
+
+// restart the computer, asking for authorization if needed
+pub async fn restart() -> Result<()> {
+    let conn = Connection::system().await?;
+    logind_zbus::manager::ManagerProxy::new(&conn)
+        .await?
+        .reboot(true)
+        .await
+}
+
+/// Energy of the internal batteries together, as reported by UPower.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Energy {
+    /// Energy stored, in Wh.
+    pub energy: f64,
+    /// Energy stored when full, in Wh.
+    pub energy_full: f64,
+    /// Power flowing in or out, in W, or 0 when unknown.
+    pub rate: f64,
+}
+
+// get the energy of the internal batteries, if they report it
+pub async fn get_energy() -> Option<Energy> {
+    let conn = system_connection().await.ok()?;
+    let mut total = Energy::default();
+    for battery in internal_batteries(conn).await.ok()? {
+        total.energy += battery.energy().await.ok()?;
+        total.energy_full += battery.energy_full().await.ok()?;
+        total.rate += battery.energy_rate().await.ok()?.abs();
+    }
+    (total.energy_full > 0.0).then_some(total)
+}
+
+// get the batteries of the computer itself, leaving out those of peripherals
+pub async fn internal_batteries(conn: &Connection) -> Result<Vec<DeviceProxy<'_>>> {
+    let mut batteries = Vec::new();
+    for path in UPowerProxy::new(conn).await?.enumerate_devices().await? {
+        let device = DeviceProxy::builder(conn).path(path)?.build().await?;
+        // battery
+        if device.type_().await? == 2 && device.power_supply().await? {
+            batteries.push(device);
+        }
+    }
+    Ok(batteries)
+}
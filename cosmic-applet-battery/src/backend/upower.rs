@@ -0,0 +1,133 @@
+//! # DBus interface proxies for: `org.freedesktop.UPower`, `org.freedesktop.UPower.Device`
+//!
+//! This code was generated by `zbus-xmlgen` `4.1.0` from DBus introspection data.
+//! Source: `org.freedesktop.UPower.xml`, `org.freedesktop.UPower.Device.xml`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the
+//! [Writing a client proxy](https://dbus.pages.freedesktop.org/zbus/client.html)
+//! section of the zbus documentation.
+//!
+
+use zbus::proxy;
+
+#[proxy(
+    interface = "org.freedesktop.UPower",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower"
+)]
+trait UPower {
+    /// EnumerateDevices method
+    fn enumerate_devices(&self) -> zbus::Result<Vec<zbus::zvariant::OwnedObjectPath>>;
+
+    /// DeviceAdded signal
+    #[zbus(signal)]
+    fn device_added(&self, device: zbus::zvariant::ObjectPath<'_>) -> zbus::Result<()>;
+
+    /// DeviceRemoved signal
+    #[zbus(signal)]
+    fn device_removed(&self, device: zbus::zvariant::ObjectPath<'_>) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.UPower.Device",
+    default_service = "org.freedesktop.UPower",
+    assume_defaults = false
+)]
+trait Device {
+    /// EnableChargeThreshold method
+    fn enable_charge_threshold(&self, charge_threshold: bool) -> zbus::Result<()>;
+
+    /// GetHistory method
+    fn get_history(
+        &self,
+        type_: &str,
+        timespan: u32,
+        resolution: u32,
+    ) -> zbus::Result<Vec<(u32, f64, u32)>>;
+
+    /// BatteryLevel property
+    #[zbus(property)]
+    fn battery_level(&self) -> zbus::Result<u32>;
+
+    /// ChargeCycles property
+    #[zbus(property)]
+    fn charge_cycles(&self) -> zbus::Result<i32>;
+
+    /// ChargeEndThreshold property
+    #[zbus(property)]
+    fn charge_end_threshold(&self) -> zbus::Result<u32>;
+
+    /// ChargeStartThreshold property
+    #[zbus(property)]
+    fn charge_start_threshold(&self) -> zbus::Result<u32>;
+
+    /// ChargeThresholdEnabled property
+    #[zbus(property)]
+    fn charge_threshold_enabled(&self) -> zbus::Result<bool>;
+
+    /// ChargeThresholdSupported property
+    #[zbus(property)]
+    fn charge_threshold_supported(&self) -> zbus::Result<bool>;
+
+    /// Energy property
+    #[zbus(property)]
+    fn energy(&self) -> zbus::Result<f64>;
+
+    /// EnergyFull property
+    #[zbus(property)]
+    fn energy_full(&self) -> zbus::Result<f64>;
+
+    /// EnergyFullDesign property
+    #[zbus(property)]
+    fn energy_full_design(&self) -> zbus::Result<f64>;
+
+    /// EnergyRate property
+    #[zbus(property)]
+    fn energy_rate(&self) -> zbus::Result<f64>;
+
+    /// IsPresent property
+    #[zbus(property)]
+    fn is_present(&self) -> zbus::Result<bool>;
+
+    /// Model property
+    #[zbus(property)]
+    fn model(&self) -> zbus::Result<String>;
+
+    /// NativePath property
+    #[zbus(property)]
+    fn native_path(&self) -> zbus::Result<String>;
+
+    /// Percentage property
+    #[zbus(property)]
+    fn percentage(&self) -> zbus::Result<f64>;
+
+    /// PowerSupply property
+    #[zbus(property)]
+    fn power_supply(&self) -> zbus::Result<bool>;
+
+    /// Serial property
+    #[zbus(property)]
+    fn serial(&self) -> zbus::Result<String>;
+
+    /// State property
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<u32>;
+
+    /// Technology property
+    #[zbus(property)]
+    fn technology(&self) -> zbus::Result<u32>;
+
+    /// Type property
+    #[zbus(property, name = "Type")]
+    fn type_(&self) -> zbus::Result<u32>;
+
+    /// Vendor property
+    #[zbus(property)]
+    fn vendor(&self) -> zbus::Result<String>;
+
+    /// Voltage property
+    #[zbus(property)]
+    fn voltage(&self) -> zbus::Result<f64>;
+}
@@ -1,4 +1,54 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{ChargeThresholds, Graphics, Power, DEFAULT_CHARGE_THRESHOLDS};
+
 pub const APP_ID: &str = "com.system76.CosmicAppletButton";
+
+#[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
+#[version = 1]
+pub struct BatteryAppletConfig {
+    /// Peripherals to warn about when their battery runs low.
+    pub peripheral_warnings: Vec<String>,
+    /// Percentage at which to notify that the battery is low, or 0 to not notify.
+    pub warning_level: u8,
+    /// Percentage at which to notify that the battery is about to run out,
+    /// or 0 to not notify.
+    pub critical_level: u8,
+    /// Notify once the battery is full, or reached its charge limit.
+    pub notify_full: bool,
+    /// Profile to switch to when plugged in.
+    pub ac_profile: Option<Power>,
+    /// Profile to switch to when running on battery.
+    pub battery_profile: Option<Power>,
+    /// Percentage below which to switch to the battery profile, or 0 to not
+    /// switch.
+    pub power_saver_level: u8,
+    /// Power off the discrete GPU once nothing uses it.
+    pub dgpu_power_off: bool,
+    /// Thresholds last applied, restored when the charge limit is turned on.
+    pub charge_thresholds: ChargeThresholds,
+    /// Boot in which another graphics mode was selected, with the mode it
+    /// runs in, as the power daemon only knows the mode of the next boot.
+    pub graphics_session: Option<(String, Graphics)>,
+}
+
+impl Default for BatteryAppletConfig {
+    fn default() -> Self {
+        Self {
+            peripheral_warnings: Vec::new(),
+            warning_level: 10,
+            critical_level: 5,
+            notify_full: true,
+            ac_profile: None,
+            battery_profile: None,
+            power_saver_level: 0,
+            dgpu_power_off: false,
+            charge_thresholds: DEFAULT_CHARGE_THRESHOLDS,
+            graphics_session: None,
+        }
+    }
+}
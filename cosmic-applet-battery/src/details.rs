@@ -0,0 +1,232 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Health and other details of the internal batteries, as reported by UPower.
+
+use cosmic::{
+    applet::padded_control,
+    iced::{alignment::Horizontal, widget::row, Length},
+    iced_widget::Column,
+    widget::text,
+    Element,
+};
+use zbus::Connection;
+
+use crate::{
+    app::{revealer_header, Message},
+    backend::{internal_batteries, upower::DeviceProxy},
+    fl,
+};
+
+const DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Technology {
+    #[default]
+    Unknown,
+    LithiumIon,
+    LithiumPolymer,
+    LithiumIronPhosphate,
+    LeadAcid,
+    NickelCadmium,
+    NickelMetalHydride,
+}
+
+impl Technology {
+    fn from_u32(technology: u32) -> Self {
+        match technology {
+            1 => Self::LithiumIon,
+            2 => Self::LithiumPolymer,
+            3 => Self::LithiumIronPhosphate,
+            4 => Self::LeadAcid,
+            5 => Self::NickelCadmium,
+            6 => Self::NickelMetalHydride,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Details of a battery, with energy in Wh, power in W and voltage in V.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BatteryDetails {
+    pub vendor: String,
+    pub model: String,
+    pub technology: Technology,
+    pub percentage: f64,
+    pub energy: f64,
+    pub energy_full: f64,
+    pub energy_full_design: f64,
+    pub energy_rate: f64,
+    pub voltage: Option<f64>,
+    /// Charge cycles, when the battery counts them.
+    pub cycles: Option<u32>,
+}
+
+impl BatteryDetails {
+    async fn new(device: &DeviceProxy<'_>) -> zbus::Result<Self> {
+        Ok(Self {
+            vendor: device.vendor().await.unwrap_or_default(),
+            model: device.model().await.unwrap_or_default(),
+            technology: Technology::from_u32(device.technology().await.unwrap_or_default()),
+            percentage: device.percentage().await?,
+            energy: device.energy().await?,
+            energy_full: device.energy_full().await?,
+            energy_full_design: device.energy_full_design().await.unwrap_or_default(),
+            energy_rate: device.energy_rate().await.unwrap_or_default(),
+            voltage: device.voltage().await.ok().filter(|voltage| *voltage > 0.0),
+            // -1 when unknown
+            cycles: device
+                .charge_cycles()
+                .await
+                .ok()
+                .and_then(|cycles| u32::try_from(cycles).ok())
+                .filter(|cycles| *cycles > 0),
+        })
+    }
+
+    /// Capacity left compared to when the battery was new, in percent.
+    pub fn health(&self) -> Option<f64> {
+        (self.energy_full_design > 0.0)
+            .then(|| (self.energy_full / self.energy_full_design * 100.0).min(100.0))
+    }
+
+    /// Capacity lost compared to when the battery was new, in percent.
+    pub fn wear(&self) -> Option<f64> {
+        self.health().map(|health| 100.0 - health)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Details {
+    /// All batteries taken as one, as shown in the panel.
+    pub display: BatteryDetails,
+    pub batteries: Vec<BatteryDetails>,
+}
+
+pub async fn get_details() -> Option<Details> {
+    let res = async {
+        let conn = Connection::system().await?;
+        let mut batteries = Vec::new();
+        for battery in internal_batteries(&conn).await? {
+            batteries.push(BatteryDetails::new(&battery).await?);
+        }
+
+        let display_device = DeviceProxy::builder(&conn)
+            .path(DISPLAY_DEVICE)?
+            .build()
+            .await?;
+        let mut display = BatteryDetails::new(&display_device).await?;
+        // only known for each battery
+        display.energy_full_design = batteries.iter().map(|b| b.energy_full_design).sum();
+        display.cycles = None;
+
+        zbus::Result::Ok(Details { display, batteries })
+    }
+    .await;
+
+    match res {
+        Ok(details) => Some(details),
+        Err(err) => {
+            tracing::error!("Failed to get battery details: {}", err);
+            None
+        }
+    }
+}
+
+// Details of the batteries below a header that shows or hides them
+pub fn details_view(details: Option<&Details>, open: bool) -> Element<'_, Message> {
+    let mut content = vec![revealer_header(
+        fl!("battery-details"),
+        open,
+        Message::ToggleDetails,
+    )];
+
+    if let Some(details) = details.filter(|_| open) {
+        // each battery separately, once there is more than one
+        if details.batteries.len() > 1 {
+            content.push(battery_view(fl!("all-batteries"), &details.display));
+            for (i, battery) in details.batteries.iter().enumerate() {
+                let title = if battery.model.is_empty() {
+                    fl!("battery-number", number = i + 1)
+                } else {
+                    battery.model.clone()
+                };
+                content.push(battery_view(title, battery));
+            }
+        } else if let Some(battery) = details.batteries.first() {
+            content.push(battery_view(battery.model.clone(), battery));
+        }
+    }
+
+    Column::with_children(content).into()
+}
+
+fn battery_view<'a>(title: String, details: &BatteryDetails) -> Element<'a, Message> {
+    let detail = |label: String, value: String| -> Element<'a, Message> {
+        padded_control(
+            row![
+                text::body(label).width(Length::Fill),
+                text::body(value).horizontal_alignment(Horizontal::Right)
+            ]
+            .spacing(8),
+        )
+        .into()
+    };
+
+    let mut content: Vec<Element<'a, Message>> = Vec::new();
+    if !title.is_empty() {
+        content.push(padded_control(text::heading(title)).into());
+    }
+    if let (Some(health), Some(wear)) = (details.health(), details.wear()) {
+        content.push(detail(
+            fl!("health"),
+            fl!(
+                "health-value",
+                health = format!("{health:.0}"),
+                wear = format!("{wear:.0}")
+            ),
+        ));
+    }
+    content.push(detail(
+        fl!("capacity"),
+        if details.energy_full_design > 0.0 {
+            format!(
+                "{:.1} / {:.1} Wh",
+                details.energy_full, details.energy_full_design
+            )
+        } else {
+            format!("{:.1} Wh", details.energy_full)
+        },
+    ));
+    content.push(detail(
+        fl!("energy"),
+        format!("{:.1} Wh ({:.0}%)", details.energy, details.percentage),
+    ));
+    content.push(detail(
+        fl!("energy-rate"),
+        format!("{:.1} W", details.energy_rate),
+    ));
+    if let Some(voltage) = details.voltage {
+        content.push(detail(fl!("voltage"), format!("{voltage:.1} V")));
+    }
+    if let Some(cycles) = details.cycles {
+        content.push(detail(fl!("charge-cycles"), cycles.to_string()));
+    }
+    let technology = match details.technology {
+        Technology::LithiumIon => Some(fl!("lithium-ion")),
+        Technology::LithiumPolymer => Some(fl!("lithium-polymer")),
+        Technology::LithiumIronPhosphate => Some(fl!("lithium-iron-phosphate")),
+        Technology::LeadAcid => Some(fl!("lead-acid")),
+        Technology::NickelCadmium => Some(fl!("nickel-cadmium")),
+        Technology::NickelMetalHydride => Some(fl!("nickel-metal-hydride")),
+        Technology::Unknown => None,
+    };
+    if let Some(technology) = technology {
+        content.push(detail(fl!("technology"), technology));
+    }
+    if !details.vendor.is_empty() {
+        content.push(detail(fl!("vendor"), details.vendor.clone()));
+    }
+
+    Column::with_children(content).into()
+}
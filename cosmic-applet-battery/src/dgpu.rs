@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use std::{
+    collections::HashSet,
     ffi::{OsStr, OsString},
     fmt::{self, Debug},
     hash::Hash,
@@ -134,6 +135,12 @@ pub struct Entry {
     pub name: String,
     pub icon: Option<String>,
     pub secondary: String,
+    /// Process using the GPU, if the entry is one.
+    pub pid: Option<u32>,
+    /// Command line the process was started with, to launch it again.
+    pub exec: Option<String>,
+    /// Name of the executable, to find the windows of the process.
+    pub executable: Option<String>,
 }
 
 #[derive(Debug)]
@@ -172,6 +179,9 @@ impl Gpu {
                     ),
                     icon: Some("display-symbolic".to_string()),
                     secondary: String::new(),
+                    pid: None,
+                    exec: None,
+                    executable: None,
                 })
                 .collect();
             // TODO read and parse edid with libdisplay-info and display output manufacture/model
@@ -225,7 +235,7 @@ impl Gpu {
                         .filter(|line| !line.starts_with('#'))
                         .map(|line| {
                             let components = line.split_whitespace().collect::<Vec<_>>();
-                            let pid = components[1].trim();
+                            let pid = &owning_process(components[1].trim());
                             let process_name = components.last().unwrap().trim();
 
                             if let Some(application) = running_apps
@@ -236,12 +246,18 @@ impl Gpu {
                                     name: application.name.clone(),
                                     icon: application.icon.clone(),
                                     secondary: String::new(),
+                                    pid: pid.parse().ok(),
+                                    exec: command_line(pid),
+                                    executable: Some(process_name.to_string()),
                                 }
                             } else {
                                 Entry {
                                     name: process_name.to_string(),
                                     icon: None,
                                     secondary: pid.to_string(),
+                                    pid: pid.parse().ok(),
+                                    exec: command_line(pid),
+                                    executable: Some(process_name.to_string()),
                                 }
                             }
                         })
@@ -265,6 +281,7 @@ impl Gpu {
                     lsof_output
                         .lines()
                         .filter_map(|pid| {
+                            let pid = &owning_process(pid);
                             let executable = std::fs::read_link(format!("/proc/{}/exe", pid))
                                 .ok()?
                                 .components()
@@ -281,12 +298,18 @@ impl Gpu {
                                     name: application.name.clone(),
                                     icon: application.icon.clone(),
                                     secondary: String::new(),
+                                    pid: pid.parse().ok(),
+                                    exec: command_line(pid),
+                                    executable: Some(executable),
                                 })
                             } else {
                                 Some(Entry {
-                                    name: executable,
+                                    name: executable.clone(),
                                     icon: None,
                                     secondary: pid.to_string(),
+                                    pid: pid.parse().ok(),
+                                    exec: command_line(pid),
+                                    executable: Some(executable),
                                 })
                             }
                         })
@@ -297,6 +320,102 @@ impl Gpu {
     }
 }
 
+// Process leading the group of `pid` when it runs the same executable, as
+// applications like browsers use the GPU from helper processes
+fn owning_process(pid: &str) -> String {
+    let leader = pid
+        .parse()
+        .ok()
+        .and_then(rustix::process::Pid::from_raw)
+        .and_then(|pid| rustix::process::getpgid(Some(pid)).ok())
+        .map(|leader| leader.as_raw_nonzero().to_string());
+    let exe = |pid: &str| std::fs::read_link(format!("/proc/{}/exe", pid)).ok();
+    match leader {
+        Some(leader) if exe(&leader).is_some() && exe(&leader) == exe(pid) => leader,
+        _ => pid.to_string(),
+    }
+}
+
+/// Whether a window belongs to an executable, as app ids usually are its
+/// name, like `firefox`, or end with it, like `org.mozilla.firefox`.
+pub fn matches_app_id(executable: &str, app_id: &str) -> bool {
+    app_id.eq_ignore_ascii_case(executable)
+        || app_id
+            .rsplit_once('.')
+            .is_some_and(|(_, name)| name.eq_ignore_ascii_case(executable))
+}
+
+/// `Exec` of the desktop entry of an application.
+pub fn desktop_exec(app_id: &str) -> Option<String> {
+    cosmic::desktop::load_applications_for_app_ids(None, std::iter::once(app_id), false, false)
+        .pop()?
+        .exec
+}
+
+// Command line of a process, quoted like the `Exec` key of desktop entries
+fn command_line(pid: &str) -> Option<String> {
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let args = cmdline
+        .split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| {
+            let arg = String::from_utf8_lossy(arg);
+            if arg.contains(|c: char| c.is_whitespace() || "\"'\\$`".contains(c)) {
+                let escaped = arg.chars().fold(String::new(), |mut escaped, c| {
+                    if "\"`$\\".contains(c) {
+                        escaped.push('\\');
+                    }
+                    escaped.push(c);
+                    escaped
+                });
+                format!("\"{}\"", escaped)
+            } else {
+                arg.into_owned()
+            }
+        })
+        .collect::<Vec<_>>();
+    (!args.is_empty()).then(|| args.join(" "))
+}
+
+/// Ask a process to quit, letting it save its state first.
+pub fn terminate(pid: u32) -> io::Result<()> {
+    let pid = i32::try_from(pid)
+        .ok()
+        .and_then(rustix::process::Pid::from_raw)
+        .ok_or(io::ErrorKind::InvalidInput)?;
+    rustix::process::kill_process(pid, rustix::process::Signal::Term)?;
+    Ok(())
+}
+
+/// Wait until a process has quit, for at most `timeout`. Returns whether it did.
+pub async fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let proc_path = PathBuf::from(format!("/proc/{}", pid));
+    let wait = async {
+        while tokio::fs::try_exists(&proc_path).await.unwrap_or(false) {
+            time::sleep(Duration::from_millis(100)).await;
+        }
+    };
+    time::timeout(timeout, wait).await.is_ok()
+}
+
+/// Environment that makes an application render on the integrated GPU, as
+/// advertised by switcheroo-control.
+pub async fn integrated_gpu_env() -> Vec<(String, String)> {
+    let gpus = async {
+        let connection = zbus::Connection::system().await.ok()?;
+        let proxy = switcheroo_control::SwitcherooControlProxy::new(&connection)
+            .await
+            .ok()?;
+        proxy.get_gpus().await.ok()
+    };
+    gpus.await
+        .unwrap_or_default()
+        .into_iter()
+        .find(|gpu| gpu.default)
+        .map(|gpu| gpu.environment.into_iter().collect())
+        .unwrap_or_default()
+}
+
 fn all_gpus<S: AsRef<str>>(seat: S) -> io::Result<Vec<Gpu>> {
     let mut enumerator = udev::Enumerator::new()?;
     enumerator.match_subsystem("drm")?;
@@ -508,10 +627,23 @@ async fn start_listening(
 
                     if enabled {
                         let mut list = gpu.connected_outputs().await.unwrap_or_default();
+                        let mut busy = false;
                         if let Some(mut apps) = gpu.app_list(&[]).await {
                             apps.retain(|app| app.name != "cosmic-comp" && app.name != "Xwayland");
+                            // helpers are listed as the application they belong to
+                            let mut pids = HashSet::new();
+                            apps.retain(|app| app.pid.map_or(true, |pid| pids.insert(pid)));
+                            busy = !apps.is_empty();
                             list.extend(apps);
                         }
+
+                        // follow applications closely, to see them release the GPU
+                        let period = Duration::from_secs(if busy { 3 } else { 30 });
+                        if gpu.interval.period() != period {
+                            let mut new_interval = time::interval_at(time::Instant::now() + period, period);
+                            new_interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+                            gpu.interval = new_interval;
+                        }
                         if output.send(GpuUpdate::On(gpu.path.clone(), gpu.name.clone(), (!list.is_empty()).then_some(list))).await.is_err() {
                             return State::Finished;
                         }
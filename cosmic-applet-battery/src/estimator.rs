@@ -0,0 +1,308 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Time until the battery is empty or full, averaged over the last minutes so
+//! that it doesn't jump around with every change in load.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::backend::Energy;
+
+/// How far back samples are averaged.
+const WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Time the samples need to cover before the charge rate is trusted.
+const MIN_SPAN: Duration = Duration::from_secs(2 * 60);
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    time: Instant,
+    percent: f64,
+    /// Energy as reported by UPower, when the battery reports its rate.
+    energy: Option<Energy>,
+    /// Time to empty as reported by UPower, when discharging.
+    time_to_empty: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Estimator {
+    samples: VecDeque<Sample>,
+    charging: bool,
+}
+
+impl Estimator {
+    /// Record the charge of the battery. Samples taken before the charger was
+    /// plugged in or out are dropped, as they no longer say anything.
+    pub fn push(
+        &mut self,
+        time: Instant,
+        percent: f64,
+        energy: Option<Energy>,
+        charging: bool,
+        time_to_empty: Duration,
+    ) {
+        if charging != self.charging {
+            self.samples.clear();
+            self.charging = charging;
+        }
+
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| time.duration_since(sample.time) > WINDOW)
+        {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(Sample {
+            time,
+            percent,
+            energy: energy.filter(|energy| energy.rate > 0.0),
+            time_to_empty: (!charging && !time_to_empty.is_zero()).then_some(time_to_empty),
+        });
+    }
+
+    /// Time until the battery is empty while discharging, or until it reaches
+    /// `full` percent while charging.
+    pub fn remaining(&self, full: f64) -> Option<Duration> {
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        if last.time.duration_since(first.time) < MIN_SPAN {
+            // until enough time went by, average what UPower reported
+            return self.reported_time_to_empty();
+        }
+
+        // the power draw is the most accurate, the change in percentage is
+        // only used when the battery doesn't report it
+        if let (Some(energy), Some(watts)) = (last.energy, self.energy_rate()) {
+            let wh = if self.charging {
+                (energy.energy_full * full / 100.0 - energy.energy).max(0.0)
+            } else {
+                energy.energy
+            };
+            return Some(Duration::from_secs_f64(wh / watts * 3600.0));
+        }
+
+        match self.percent_rate() {
+            Some(rate) if self.charging && rate > 0.0 => Some(Duration::from_secs_f64(
+                (full - last.percent).max(0.0) / rate,
+            )),
+            Some(rate) if !self.charging && rate < 0.0 => {
+                Some(Duration::from_secs_f64(last.percent / -rate))
+            }
+            _ => self.reported_time_to_empty(),
+        }
+    }
+
+    // Average power over the window, in W
+    fn energy_rate(&self) -> Option<f64> {
+        let rates = self
+            .samples
+            .iter()
+            .filter_map(|sample| sample.energy.map(|energy| energy.rate))
+            .collect::<Vec<_>>();
+        (!rates.is_empty()).then(|| rates.iter().sum::<f64>() / rates.len() as f64)
+    }
+
+    // Percent gained per second, negative when discharging
+    fn percent_rate(&self) -> Option<f64> {
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        let span = last.time.duration_since(first.time);
+        if first.percent == last.percent {
+            return None;
+        }
+        Some((last.percent - first.percent) / span.as_secs_f64())
+    }
+
+    fn reported_time_to_empty(&self) -> Option<Duration> {
+        if self.charging {
+            return None;
+        }
+        let reported = self
+            .samples
+            .iter()
+            .filter_map(|sample| sample.time_to_empty)
+            .collect::<Vec<_>>();
+        let count = u32::try_from(reported.len())
+            .ok()
+            .filter(|count| *count > 0)?;
+        Some(reported.into_iter().sum::<Duration>() / count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn energy(energy: f64, rate: f64) -> Option<Energy> {
+        Some(Energy {
+            energy,
+            energy_full: 50.0,
+            rate,
+        })
+    }
+
+    fn secs(remaining: Option<Duration>) -> Option<u64> {
+        remaining.map(|remaining| remaining.as_secs_f64().round() as u64)
+    }
+
+    // One sample a minute, from the first percentage by `step` each
+    fn percent_samples(
+        charging: bool,
+        samples: u32,
+        start: f64,
+        step: f64,
+    ) -> (Estimator, Instant) {
+        let mut estimator = Estimator::default();
+        let begin = Instant::now();
+        for i in 0..samples {
+            estimator.push(
+                begin + MINUTE * i,
+                start + step * f64::from(i),
+                None,
+                charging,
+                Duration::ZERO,
+            );
+        }
+        (estimator, begin + MINUTE * samples.saturating_sub(1))
+    }
+
+    #[test]
+    fn steady_discharge() {
+        // 1% a minute, with 50% left
+        let (estimator, _) = percent_samples(false, 11, 60.0, -1.0);
+        assert_eq!(
+            secs(estimator.remaining(100.0)),
+            Some((MINUTE * 50).as_secs())
+        );
+    }
+
+    #[test]
+    fn steady_discharge_energy() {
+        let mut estimator = Estimator::default();
+        let now = Instant::now();
+        for (i, rate) in [8.0, 12.0, 10.0].into_iter().enumerate() {
+            estimator.push(
+                now + MINUTE * i as u32,
+                50.0,
+                energy(25.0, rate),
+                false,
+                Duration::ZERO,
+            );
+        }
+        // 25 Wh at an average of 10 W
+        assert_eq!(
+            secs(estimator.remaining(100.0)),
+            Some((MINUTE * 150).as_secs())
+        );
+    }
+
+    #[test]
+    fn steady_charge_below_full() {
+        // 1% a minute, at 50% and stopping at 80%
+        let (estimator, _) = percent_samples(true, 11, 40.0, 1.0);
+        assert_eq!(
+            secs(estimator.remaining(80.0)),
+            Some((MINUTE * 30).as_secs())
+        );
+
+        let mut estimator = Estimator::default();
+        let now = Instant::now();
+        for i in 0..3 {
+            estimator.push(
+                now + MINUTE * i,
+                50.0,
+                energy(25.0, 10.0),
+                true,
+                Duration::ZERO,
+            );
+        }
+        // 15 Wh to go until 40 of 50 Wh, at 10 W
+        assert_eq!(
+            secs(estimator.remaining(80.0)),
+            Some((MINUTE * 90).as_secs())
+        );
+        // already above the limit
+        assert_eq!(estimator.remaining(40.0), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn reset_on_plug() {
+        let (mut estimator, last) = percent_samples(false, 11, 60.0, -1.0);
+        estimator.push(last + MINUTE, 50.0, None, true, Duration::ZERO);
+        assert_eq!(estimator.samples.len(), 1);
+        assert_eq!(estimator.remaining(100.0), None);
+
+        estimator.push(last + MINUTE * 2, 51.0, None, false, MINUTE * 40);
+        assert_eq!(estimator.samples.len(), 1);
+        assert_eq!(
+            secs(estimator.remaining(100.0)),
+            Some((MINUTE * 40).as_secs())
+        );
+    }
+
+    #[test]
+    fn shorter_than_min_span() {
+        let (estimator, _) = percent_samples(false, 2, 60.0, -1.0);
+        assert_eq!(estimator.remaining(100.0), None);
+
+        let mut estimator = Estimator::default();
+        let now = Instant::now();
+        estimator.push(now, 60.0, energy(30.0, 10.0), false, MINUTE * 30);
+        estimator.push(now + MINUTE, 59.0, energy(29.0, 10.0), false, MINUTE * 50);
+        // what UPower reported, averaged
+        assert_eq!(
+            secs(estimator.remaining(100.0)),
+            Some((MINUTE * 40).as_secs())
+        );
+    }
+
+    #[test]
+    fn old_samples_leave_window() {
+        let mut estimator = Estimator::default();
+        let now = Instant::now();
+        estimator.push(now, 80.0, energy(40.0, 40.0), false, Duration::ZERO);
+        for i in 10..=30 {
+            estimator.push(
+                now + MINUTE * i,
+                50.0,
+                energy(25.0, 10.0),
+                false,
+                Duration::ZERO,
+            );
+        }
+        assert!(estimator
+            .samples
+            .iter()
+            .all(|sample| sample.time >= now + MINUTE * 15));
+        // the early high draw no longer counts
+        assert_eq!(
+            secs(estimator.remaining(100.0)),
+            Some((MINUTE * 150).as_secs())
+        );
+    }
+
+    #[test]
+    fn flat_percentage() {
+        let (estimator, _) = percent_samples(false, 11, 50.0, 0.0);
+        assert_eq!(estimator.remaining(100.0), None);
+
+        let (estimator, _) = percent_samples(true, 11, 50.0, 0.0);
+        assert_eq!(estimator.remaining(100.0), None);
+
+        // falls back to what UPower reported
+        let mut estimator = Estimator::default();
+        let now = Instant::now();
+        for i in 0..5 {
+            estimator.push(now + MINUTE * i, 50.0, None, false, MINUTE * 60);
+        }
+        assert_eq!(
+            secs(estimator.remaining(100.0)),
+            Some((MINUTE * 60).as_secs())
+        );
+    }
+}
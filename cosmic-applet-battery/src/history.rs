@@ -0,0 +1,181 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Charge and power draw of the battery over the last hours, as recorded by UPower.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cosmic::{
+    iced::{
+        widget::{column, container, row},
+        Alignment, Length,
+    },
+    iced_core::{Background, Border, Color, Shadow},
+    iced_widget::Row,
+    widget::{button, text, vertical_space},
+    Element, Theme,
+};
+
+use crate::{
+    app::Message,
+    backend::{internal_batteries, system_connection},
+    fl,
+};
+
+/// How far back the history goes.
+pub const TIMESPAN: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Number of bars in the graph.
+pub const BUCKETS: usize = 48;
+
+// Height of the graph
+const HEIGHT: f32 = 48.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HistoryKind {
+    /// Percentage of the battery.
+    #[default]
+    Charge,
+    /// Power draw in watts.
+    Rate,
+}
+
+impl HistoryKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Charge => "charge",
+            Self::Rate => "rate",
+        }
+    }
+}
+
+/// Value of each slice of the timespan, oldest first, or `None` where nothing
+/// was recorded. The charge is averaged over the batteries, while their power
+/// draw adds up.
+pub async fn get_history(kind: HistoryKind) -> Vec<Option<f64>> {
+    let batteries = match samples(kind).await {
+        Ok(samples) => samples,
+        Err(err) => {
+            tracing::error!("Failed to get battery history: {}", err);
+            return Vec::new();
+        }
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let start = now.saturating_sub(TIMESPAN.as_secs());
+    let bucket_secs = TIMESPAN.as_secs() / BUCKETS as u64;
+
+    // average each battery on its own, as they are not sampled at the same
+    // times, then combine them
+    let mut buckets = vec![(0.0, 0); BUCKETS];
+    for samples in batteries {
+        let mut battery = vec![(0.0, 0); BUCKETS];
+        for (time, value) in samples {
+            let Some(offset) = time.checked_sub(start) else {
+                continue;
+            };
+            let index = (offset / bucket_secs) as usize;
+            if let Some((sum, count)) = battery.get_mut(index.min(BUCKETS - 1)) {
+                *sum += value;
+                *count += 1;
+            }
+        }
+
+        for ((sum, count), (battery_sum, battery_count)) in buckets.iter_mut().zip(battery) {
+            if battery_count > 0 {
+                *sum += battery_sum / battery_count as f64;
+                *count += 1;
+            }
+        }
+    }
+
+    buckets
+        .into_iter()
+        .map(|(sum, count)| {
+            (count > 0).then(|| match kind {
+                HistoryKind::Charge => sum / count as f64,
+                HistoryKind::Rate => sum,
+            })
+        })
+        .collect()
+}
+
+// Graph of the charge or power draw, with tabs to switch between them
+pub fn history_view<'a>(kind: HistoryKind, history: &[Option<f64>]) -> Element<'a, Message> {
+    let max = match kind {
+        HistoryKind::Charge => 100.0,
+        HistoryKind::Rate => history.iter().flatten().fold(1.0, |a: f64, b| a.max(*b)),
+    };
+    let bars = history.iter().map(|value| {
+        let height = value.map_or(0.0, |value| (value / max).clamp(0.0, 1.0)) as f32;
+        container(vertical_space(Length::Fixed(height * HEIGHT)))
+            .width(Length::Fill)
+            .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+                |theme| container::Appearance {
+                    text_color: Some(Color::TRANSPARENT),
+                    background: Some(Background::Color(theme.cosmic().accent_color().into())),
+                    border: Border {
+                        radius: 1.0.into(),
+                        width: 0.0,
+                        color: Color::TRANSPARENT,
+                    },
+                    shadow: Shadow::default(),
+                    icon_color: Some(Color::TRANSPARENT),
+                },
+            )))
+            .into()
+    });
+    let tab = |tab: HistoryKind, label: String| {
+        button::text(label)
+            .style(if tab == kind {
+                cosmic::theme::Button::Standard
+            } else {
+                cosmic::theme::Button::Text
+            })
+            .on_press(Message::SelectHistory(tab))
+    };
+    let caption = match kind {
+        HistoryKind::Charge => fl!("history-charge-caption"),
+        HistoryKind::Rate => fl!("history-rate-caption", watts = format!("{max:.1}")),
+    };
+
+    column![
+        row![
+            text::heading(fl!("history")).width(Length::Fill),
+            tab(HistoryKind::Charge, fl!("history-charge")),
+            tab(HistoryKind::Rate, fl!("history-rate")),
+        ]
+        .spacing(4)
+        .align_items(Alignment::Center),
+        Row::with_children(bars.collect())
+            .spacing(1)
+            .height(Length::Fixed(HEIGHT))
+            .align_items(Alignment::End),
+        text::caption(caption),
+    ]
+    .spacing(8)
+    .into()
+}
+
+// Samples of each internal battery, as time and value
+async fn samples(kind: HistoryKind) -> zbus::Result<Vec<Vec<(u64, f64)>>> {
+    let conn = system_connection().await?;
+    let mut samples = Vec::new();
+    for battery in internal_batteries(conn).await? {
+        let history = battery
+            .get_history(kind.as_str(), TIMESPAN.as_secs() as u32, BUCKETS as u32 * 4)
+            .await?;
+        samples.push(
+            history
+                .into_iter()
+                // unknown state, recorded while UPower was not sure
+                .filter(|(_, _, state)| *state != 0)
+                .map(|(time, value, _)| (u64::from(time), value))
+                .collect(),
+        );
+    }
+    Ok(samples)
+}
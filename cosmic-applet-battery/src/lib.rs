@@ -4,8 +4,14 @@
 mod app;
 mod backend;
 mod config;
+mod details;
 mod dgpu;
+mod estimator;
+mod history;
 mod localize;
+mod notifications;
+mod peripherals;
+mod wayland_subscription;
 
 use localize::localize;
 
@@ -0,0 +1,154 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Desktop notifications about the battery, through `org.freedesktop.Notifications`.
+
+use std::collections::HashMap;
+
+use cosmic::{
+    applet::padded_control,
+    iced::{
+        alignment::Horizontal,
+        widget::{column, row, slider},
+        Alignment, Length,
+    },
+    widget::text,
+    Element,
+};
+use cosmic_time::{anim, id, once_cell::sync::Lazy, Timeline};
+use zbus::{proxy, zvariant::Value, Connection};
+
+use crate::{app::Message, config::BatteryAppletConfig, fl};
+
+static NOTIFY_FULL: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    /// Notify method
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, &Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    /// CloseNotification method
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low = 0,
+    Normal = 1,
+    Critical = 2,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+    pub icon: String,
+    pub urgency: Urgency,
+}
+
+/// Show a notification in place of the one with the id `replaces`, or as a
+/// new one when it is 0, and return its id.
+pub async fn notify(conn: Connection, replaces: u32, notification: Notification) -> Option<u32> {
+    let res = async {
+        let proxy = NotificationsProxy::new(&conn).await?;
+        let urgency = Value::U8(notification.urgency as u8);
+        proxy
+            .notify(
+                &fl!("battery"),
+                replaces,
+                &notification.icon,
+                &notification.summary,
+                &notification.body,
+                &[],
+                HashMap::from([("urgency", &urgency)]),
+                -1,
+            )
+            .await
+    }
+    .await;
+
+    match res {
+        Ok(id) => Some(id),
+        Err(err) => {
+            tracing::error!("Failed to show notification: {}", err);
+            None
+        }
+    }
+}
+
+pub async fn close(conn: Connection, id: u32) {
+    let res = match NotificationsProxy::new(&conn).await {
+        Ok(proxy) => proxy.close_notification(id).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = res {
+        tracing::error!("Failed to close notification: {}", err);
+    }
+}
+
+// Levels at which users are notified, and whether to tell when the battery is full
+pub fn settings_view<'a>(
+    config: &BatteryAppletConfig,
+    timeline: &'a Timeline,
+) -> Element<'a, Message> {
+    let level_slider = |label: String, range, level: u8, message: fn(u8) -> Message| {
+        padded_control(
+            row![
+                text::body(label).width(Length::Fixed(80.0)),
+                slider(range, level, message).on_release(Message::SaveConfig),
+                text(if level == 0 {
+                    fl!("off")
+                } else {
+                    format!("{level}%")
+                })
+                .size(16)
+                .width(Length::Fixed(40.0))
+                .horizontal_alignment(Horizontal::Right)
+            ]
+            .spacing(12)
+            .align_items(Alignment::Center),
+        )
+    };
+
+    column![
+        level_slider(
+            fl!("warning-level"),
+            0..=50,
+            config.warning_level,
+            Message::SetWarningLevel
+        ),
+        level_slider(
+            fl!("critical-level"),
+            0..=20,
+            config.critical_level,
+            Message::SetCriticalLevel
+        ),
+        padded_control(
+            anim!(
+                NOTIFY_FULL,
+                timeline,
+                fl!("notify-full"),
+                config.notify_full,
+                Message::SetNotifyFull,
+            )
+            .text_size(14)
+            .width(Length::Fill),
+        ),
+    ]
+    .into()
+}
@@ -0,0 +1,262 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Batteries of mice, keyboards, headsets and other devices enumerated by UPower.
+
+use std::{fmt::Debug, hash::Hash};
+
+use cosmic::iced::{self, subscription};
+use futures::{SinkExt, StreamExt};
+use zbus::{fdo::PropertiesProxy, zvariant::OwnedObjectPath, Connection};
+
+use crate::backend::upower::{DeviceProxy, UPowerProxy};
+
+const UPOWER: &str = "org.freedesktop.UPower";
+
+/// Kind of device, from the UPower device type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Mouse,
+    Keyboard,
+    Phone,
+    Tablet,
+    Gamepad,
+    Pen,
+    Touchpad,
+    Headset,
+    Headphones,
+    Speakers,
+    RemoteControl,
+    Other,
+}
+
+impl DeviceKind {
+    fn from_type(type_: u32) -> Self {
+        match type_ {
+            5 => Self::Mouse,
+            6 => Self::Keyboard,
+            8 => Self::Phone,
+            10 => Self::Tablet,
+            12 => Self::Gamepad,
+            13 => Self::Pen,
+            14 => Self::Touchpad,
+            17 => Self::Headset,
+            19 => Self::Headphones,
+            18 | 21 => Self::Speakers,
+            22 => Self::RemoteControl,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Self::Mouse => "input-mouse-symbolic",
+            Self::Keyboard => "input-keyboard-symbolic",
+            Self::Phone => "phone-symbolic",
+            Self::Tablet | Self::Pen => "input-tablet-symbolic",
+            Self::Gamepad => "input-gaming-symbolic",
+            Self::Touchpad => "input-touchpad-symbolic",
+            Self::Headset => "audio-headset-symbolic",
+            Self::Headphones => "audio-headphones-symbolic",
+            Self::Speakers => "audio-speakers-symbolic",
+            Self::RemoteControl => "input-dialpad-symbolic",
+            Self::Other => "battery-symbolic",
+        }
+    }
+}
+
+/// How charged a device is. Some devices only report a coarse level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Charge {
+    Percent(f64),
+    Critical,
+    Low,
+    Normal,
+    High,
+    Full,
+    Unknown,
+}
+
+impl Charge {
+    fn new(level: u32, percentage: f64) -> Self {
+        match level {
+            3 => Self::Low,
+            4 => Self::Critical,
+            6 => Self::Normal,
+            7 => Self::High,
+            8 => Self::Full,
+            // the device reports a percentage instead
+            1 => Self::Percent(percentage),
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Whether the device should be charged soon.
+    pub fn is_low(self) -> bool {
+        match self {
+            Self::Percent(percent) => percent <= LOW_PERCENT,
+            Self::Critical | Self::Low => true,
+            _ => false,
+        }
+    }
+}
+
+/// Percentage at or below which a peripheral battery counts as low.
+pub const LOW_PERCENT: f64 = 20.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peripheral {
+    /// Identifies the device across reconnections.
+    pub id: String,
+    pub name: String,
+    pub kind: DeviceKind,
+    pub charge: Charge,
+    pub charging: bool,
+}
+
+impl Peripheral {
+    // Internal batteries and power supplies are left out
+    async fn new(conn: &Connection, path: &OwnedObjectPath) -> zbus::Result<Option<Self>> {
+        let device = DeviceProxy::builder(conn)
+            .path(path.clone())?
+            .build()
+            .await?;
+        if device.power_supply().await? || !device.is_present().await.unwrap_or(true) {
+            return Ok(None);
+        }
+
+        let type_ = device.type_().await?;
+        // line power, battery, UPS and monitor
+        if (1..=4).contains(&type_) {
+            return Ok(None);
+        }
+
+        let model = device.model().await.unwrap_or_default();
+        let serial = device.serial().await.unwrap_or_default();
+        let kind = DeviceKind::from_type(type_);
+        let name = if model.is_empty() {
+            device.vendor().await.unwrap_or_default()
+        } else {
+            model.clone()
+        };
+        let id = if serial.is_empty() {
+            device.native_path().await.unwrap_or_default()
+        } else {
+            format!("{model}:{serial}")
+        };
+        let charge = Charge::new(
+            device.battery_level().await.unwrap_or_default(),
+            device.percentage().await.unwrap_or_default(),
+        );
+        // charging or waiting to charge
+        let charging = matches!(device.state().await.unwrap_or_default(), 1 | 5);
+
+        Ok(Some(Self {
+            id,
+            name,
+            kind,
+            charge,
+            charging,
+        }))
+    }
+}
+
+pub fn peripheral_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<Vec<Peripheral>> {
+    subscription::channel(id, 50, move |mut output| async move {
+        let mut state = State::Ready;
+
+        loop {
+            state = start_listening(state, &mut output).await;
+        }
+    })
+}
+
+#[derive(Debug)]
+pub enum State {
+    Ready,
+    Waiting(Connection, UPowerProxy<'static>),
+    Finished,
+}
+
+async fn start_listening(
+    state: State,
+    output: &mut futures::channel::mpsc::Sender<Vec<Peripheral>>,
+) -> State {
+    match state {
+        State::Ready => {
+            let Ok(conn) = Connection::system().await else {
+                return State::Finished;
+            };
+            match UPowerProxy::new(&conn).await {
+                Ok(upower) => State::Waiting(conn, upower),
+                Err(err) => {
+                    tracing::error!("Failed to connect to UPower: {}", err);
+                    State::Finished
+                }
+            }
+        }
+        State::Waiting(conn, upower) => {
+            // listen before reading, to not miss a device in between
+            let (Ok(added), Ok(removed)) = (
+                upower.receive_device_added().await,
+                upower.receive_device_removed().await,
+            ) else {
+                return State::Finished;
+            };
+            let mut changes = futures::stream::select_all(vec![
+                added.map(|_| ()).boxed(),
+                removed.map(|_| ()).boxed(),
+            ]);
+
+            let paths = match upower.enumerate_devices().await {
+                Ok(paths) => paths,
+                Err(err) => {
+                    tracing::error!("Failed to enumerate UPower devices: {}", err);
+                    return State::Finished;
+                }
+            };
+
+            let mut peripherals = Vec::new();
+            for path in paths {
+                match Peripheral::new(&conn, &path).await {
+                    Ok(Some(peripheral)) => {
+                        // the internal battery changes too often to follow here
+                        if let Ok(properties) = properties_changed(&conn, &path).await {
+                            changes.push(properties);
+                        }
+                        peripherals.push(peripheral);
+                    }
+                    Ok(None) => {}
+                    Err(err) => tracing::debug!("Failed to read UPower device: {}", err),
+                }
+            }
+            peripherals.sort_by(|a, b| a.name.cmp(&b.name));
+
+            if output.send(peripherals).await.is_err() {
+                return State::Finished;
+            }
+
+            changes.next().await;
+            State::Waiting(conn, upower)
+        }
+        State::Finished => iced::futures::future::pending().await,
+    }
+}
+
+async fn properties_changed(
+    conn: &Connection,
+    path: &OwnedObjectPath,
+) -> zbus::Result<futures::stream::BoxStream<'static, ()>> {
+    let properties = PropertiesProxy::builder(conn)
+        .destination(UPOWER)?
+        .path(path.clone())?
+        .build()
+        .await?;
+    Ok(properties
+        .receive_properties_changed()
+        .await?
+        .map(|_| ())
+        .boxed())
+}
@@ -0,0 +1,271 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Windows of the session, to close applications the way the app list does.
+
+use std::{
+    error::Error,
+    os::{
+        fd::{FromRawFd, RawFd},
+        unix::net::UnixStream,
+    },
+};
+
+use cctk::{
+    sctk::{
+        self,
+        reexports::{calloop, calloop_wayland_source::WaylandSource},
+        registry::{ProvidesRegistryState, RegistryState},
+    },
+    toplevel_info::{ToplevelInfo, ToplevelInfoHandler, ToplevelInfoState},
+    toplevel_management::{ToplevelManagerHandler, ToplevelManagerState},
+    wayland_client::{globals::registry_queue_init, Connection, Proxy, QueueHandle, WEnum},
+};
+use cosmic::{cctk, cctk::cosmic_protocols, iced, iced::subscription, iced_futures::futures};
+use cosmic_protocols::{
+    toplevel_info::v1::client::{
+        zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1,
+        zcosmic_toplevel_info_v1::ZcosmicToplevelInfoV1,
+    },
+    toplevel_management::v1::client::zcosmic_toplevel_manager_v1::{
+        self, ZcosmicToplevelManagerV1,
+    },
+};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    SinkExt, StreamExt,
+};
+use once_cell::sync::Lazy;
+use tokio::sync::Mutex;
+
+pub static WAYLAND_RX: Lazy<Mutex<Option<UnboundedReceiver<WaylandUpdate>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+pub fn wayland_subscription() -> iced::Subscription<WaylandUpdate> {
+    subscription::channel(
+        std::any::TypeId::of::<WaylandUpdate>(),
+        50,
+        move |mut output| async move {
+            let mut state = State::Waiting;
+
+            loop {
+                state = start_listening(state, &mut output).await;
+            }
+        },
+    )
+}
+
+pub enum State {
+    Waiting,
+    Finished,
+}
+
+async fn start_listening(
+    state: State,
+    output: &mut futures::channel::mpsc::Sender<WaylandUpdate>,
+) -> State {
+    match state {
+        State::Waiting => {
+            let mut guard = WAYLAND_RX.lock().await;
+            let rx = {
+                if guard.is_none() {
+                    let (calloop_tx, calloop_rx) = calloop::channel::channel();
+                    let (toplevel_tx, toplevel_rx) = unbounded();
+                    let _ = std::thread::spawn(move || {
+                        wayland_handler(toplevel_tx, calloop_rx);
+                    });
+                    *guard = Some(toplevel_rx);
+                    _ = output.send(WaylandUpdate::Init(calloop_tx)).await;
+                }
+                guard.as_mut().unwrap()
+            };
+            match rx.next().await {
+                Some(u) => {
+                    _ = output.send(u).await;
+                    State::Waiting
+                }
+                None => {
+                    // applications are asked to quit through signals instead
+                    _ = output.send(WaylandUpdate::Finished).await;
+                    State::Finished
+                }
+            }
+        }
+        State::Finished => iced::futures::future::pending().await,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum WaylandUpdate {
+    Init(calloop::channel::Sender<ToplevelRequest>),
+    Finished,
+    Toplevel(ToplevelUpdate),
+}
+
+#[derive(Clone, Debug)]
+pub enum ToplevelUpdate {
+    Update(ZcosmicToplevelHandleV1, ToplevelInfo),
+    Remove(ZcosmicToplevelHandleV1),
+}
+
+#[derive(Debug, Clone)]
+pub enum ToplevelRequest {
+    Close(ZcosmicToplevelHandleV1),
+}
+
+struct AppData {
+    exit: bool,
+    tx: UnboundedSender<WaylandUpdate>,
+    registry_state: RegistryState,
+    toplevel_info_state: ToplevelInfoState,
+    toplevel_manager_state: ToplevelManagerState,
+}
+
+impl AppData {
+    fn send_info(&self, toplevel: &ZcosmicToplevelHandleV1) {
+        if let Some(info) = self.toplevel_info_state.info(toplevel) {
+            let _ = self
+                .tx
+                .unbounded_send(WaylandUpdate::Toplevel(ToplevelUpdate::Update(
+                    toplevel.clone(),
+                    info.clone(),
+                )));
+        }
+    }
+}
+
+impl ProvidesRegistryState for AppData {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    sctk::registry_handlers!();
+}
+
+impl ToplevelManagerHandler for AppData {
+    fn toplevel_manager_state(&mut self) -> &mut ToplevelManagerState {
+        &mut self.toplevel_manager_state
+    }
+
+    fn capabilities(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: Vec<WEnum<zcosmic_toplevel_manager_v1::ZcosmicToplelevelManagementCapabilitiesV1>>,
+    ) {
+    }
+}
+
+impl ToplevelInfoHandler for AppData {
+    fn toplevel_info_state(&mut self) -> &mut ToplevelInfoState {
+        &mut self.toplevel_info_state
+    }
+
+    fn new_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ZcosmicToplevelHandleV1,
+    ) {
+        self.send_info(toplevel);
+    }
+
+    fn update_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ZcosmicToplevelHandleV1,
+    ) {
+        self.send_info(toplevel);
+    }
+
+    fn toplevel_closed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ZcosmicToplevelHandleV1,
+    ) {
+        let _ = self
+            .tx
+            .unbounded_send(WaylandUpdate::Toplevel(ToplevelUpdate::Remove(
+                toplevel.clone(),
+            )));
+    }
+}
+
+// Ends without panicking when the compositor can't be reached or lacks the
+// protocols, which closes the channel and leaves quitting to signals
+fn wayland_handler(
+    tx: UnboundedSender<WaylandUpdate>,
+    rx: calloop::channel::Channel<ToplevelRequest>,
+) {
+    if let Err(err) = handle_toplevels(tx, rx) {
+        tracing::warn!("Can't close windows of applications: {}", err);
+    }
+}
+
+fn handle_toplevels(
+    tx: UnboundedSender<WaylandUpdate>,
+    rx: calloop::channel::Channel<ToplevelRequest>,
+) -> Result<(), Box<dyn Error>> {
+    let socket = std::env::var("X_PRIVILEGED_WAYLAND_SOCKET")
+        .ok()
+        .and_then(|fd| {
+            fd.parse::<RawFd>()
+                .ok()
+                .map(|fd| unsafe { UnixStream::from_raw_fd(fd) })
+        });
+
+    let conn = if let Some(socket) = socket {
+        Connection::from_socket(socket)?
+    } else {
+        Connection::connect_to_env()?
+    };
+    let (globals, event_queue) = registry_queue_init(&conn)?;
+    for interface in [
+        ZcosmicToplevelInfoV1::interface(),
+        ZcosmicToplevelManagerV1::interface(),
+    ] {
+        let available = globals
+            .contents()
+            .with_list(|list| list.iter().any(|global| global.interface == interface.name));
+        if !available {
+            return Err(format!("{} is not available", interface.name).into());
+        }
+    }
+
+    let mut event_loop = calloop::EventLoop::<AppData>::try_new()?;
+    let qh = event_queue.handle();
+    let handle = event_loop.handle();
+    WaylandSource::new(conn, event_queue)
+        .insert(handle.clone())
+        .map_err(|err| err.error)?;
+    handle
+        .insert_source(rx, |event, _, state| match event {
+            calloop::channel::Event::Msg(ToplevelRequest::Close(handle)) => {
+                state.toplevel_manager_state.manager.close(&handle);
+            }
+            calloop::channel::Event::Closed => {
+                state.exit = true;
+            }
+        })
+        .map_err(|err| err.error)?;
+
+    let registry_state = RegistryState::new(&globals);
+    let mut app_data = AppData {
+        exit: false,
+        tx,
+        toplevel_info_state: ToplevelInfoState::new(&registry_state, &qh),
+        toplevel_manager_state: ToplevelManagerState::new(&registry_state, &qh),
+        registry_state,
+    };
+
+    while !app_data.exit {
+        event_loop.dispatch(None, &mut app_data)?;
+    }
+    Ok(())
+}
+
+sctk::delegate_registry!(AppData);
+cctk::delegate_toplevel_info!(AppData);
+cctk::delegate_toplevel_manager!(AppData);